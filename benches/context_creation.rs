@@ -0,0 +1,71 @@
+//! Measures how long creating an `Fsr2Context` takes, on its own and through an `Fsr2Device`
+//! shared by several contexts.
+//!
+//! Run with `cargo bench --bench context_creation`. Needs a Vulkan adapter.
+
+use fsr2_wgpu::{Fsr2Context, Fsr2ContextDescriptor, Fsr2Device};
+use glam::UVec2;
+use std::time::{Duration, Instant};
+use wgpu::{Backends, DeviceDescriptor, Instance, InstanceDescriptor, RequestAdapterOptions};
+
+const CONTEXT_COUNT: u32 = 8;
+
+fn main() {
+    let instance = Instance::new(InstanceDescriptor {
+        backends: Backends::VULKAN,
+        ..Default::default()
+    });
+    let Some(adapter) =
+        pollster::block_on(instance.request_adapter(&RequestAdapterOptions::default()))
+    else {
+        eprintln!("No Vulkan adapter available");
+        return;
+    };
+    let (device, _queue) =
+        pollster::block_on(adapter.request_device(&DeviceDescriptor::default(), None)).unwrap();
+    println!("{}", adapter.get_info().name);
+
+    let descriptor = |i: u32| {
+        // Vary the resolution, as with one context per viewport
+        let upscaled_resolution = UVec2::new(1280 + 64 * i, 720 + 36 * i);
+        Fsr2ContextDescriptor::new(upscaled_resolution, upscaled_resolution)
+    };
+
+    let start = Instant::now();
+    let contexts = (0..CONTEXT_COUNT)
+        .map(|i| Fsr2Context::new(&device, &adapter, descriptor(i)).unwrap())
+        .collect::<Vec<_>>();
+    report("Fsr2Context::new", start.elapsed());
+    drop(contexts);
+
+    let fsr2_device = Fsr2Device::new(&device, &adapter).unwrap();
+    let start = Instant::now();
+    let first_context = fsr2_device.create_context(descriptor(0)).unwrap();
+    println!("Fsr2Device::create_context (first): {:?}", start.elapsed());
+    let start = Instant::now();
+    let contexts = (1..=CONTEXT_COUNT)
+        .map(|i| fsr2_device.create_context(descriptor(i)).unwrap())
+        .collect::<Vec<_>>();
+    report("Fsr2Device::create_context (cached)", start.elapsed());
+    drop((first_context, contexts));
+
+    let pipeline_cache_data = fsr2_device.pipeline_cache_data().unwrap();
+    drop(fsr2_device);
+    let fsr2_device =
+        Fsr2Device::with_pipeline_cache_data(&device, &adapter, &pipeline_cache_data).unwrap();
+    let start = Instant::now();
+    let context = fsr2_device.create_context(descriptor(0)).unwrap();
+    println!(
+        "Fsr2Device::create_context (restored {} byte cache): {:?}",
+        pipeline_cache_data.len(),
+        start.elapsed()
+    );
+    drop(context);
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{name}: {elapsed:?} for {CONTEXT_COUNT} contexts, {:?} each",
+        elapsed / CONTEXT_COUNT
+    );
+}
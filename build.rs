@@ -45,6 +45,7 @@ fn main() {
         .blocklist_type("VkDevice")
         .blocklist_type("VkImage")
         .blocklist_type("VkImageView")
+        .blocklist_type("VkImageLayout")
         .blocklist_type("VkFormat")
         .blocklist_type("VkCommandBuffer")
         .blocklist_type("PFN_vkGetDeviceProcAddr")
@@ -0,0 +1,252 @@
+use crate::capabilities::async_compute_queue_family;
+use crate::viewport::is_depth_format;
+use crate::{Fsr2Exposure, Fsr2ReactiveMask, Fsr2RenderParameters, Fsr2Texture};
+use arrayvec::ArrayVec;
+use ash::vk::{
+    AccessFlags, CommandBuffer, DependencyFlags, ImageAspectFlags, ImageLayout, ImageMemoryBarrier,
+    ImageSubresourceRange, PipelineStageFlags, QueueFlags,
+};
+use wgpu::util::CommandEncoderExt;
+use wgpu::{Adapter, CommandEncoder, Device, TextureFormat};
+use wgpu_core::api::Vulkan;
+use wgpu_core::track::TextureSelector;
+use wgpu_hal::TextureUses;
+
+/// The queue families [`crate::Fsr2Context::render_on_compute`] is recorded for. See
+/// [`crate::Fsr2ContextDescriptor::queue_families`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fsr2QueueFamilies {
+    /// The family of the queue rendering FSR's inputs and reading its output.
+    pub graphics: u32,
+    /// The family of the queue FSR is dispatched on.
+    pub compute: u32,
+}
+
+impl Fsr2QueueFamilies {
+    /// Dispatch on the `compute` family if there is one, such as
+    /// [`crate::Fsr2Capabilities::async_compute_queue_family`], or else on the `graphics` one.
+    pub fn new(graphics: u32, compute: Option<u32>) -> Self {
+        Self {
+            graphics,
+            compute: compute.unwrap_or(graphics),
+        }
+    }
+
+    /// The first family of a Vulkan `adapter` that supports graphics, and its async compute family
+    /// if it has one, to create a device with queues of both families. `None` if the adapter
+    /// isn't a Vulkan one.
+    pub fn from_adapter(adapter: &Adapter) -> Option<Self> {
+        unsafe {
+            adapter.as_hal::<Vulkan, _, _>(|adapter| {
+                let adapter = adapter?;
+                let families = adapter
+                    .shared_instance()
+                    .raw_instance()
+                    .get_physical_device_queue_family_properties(adapter.raw_physical_device());
+                let graphics = families
+                    .iter()
+                    .position(|family| family.queue_flags.contains(QueueFlags::GRAPHICS))?;
+                Some(Self::new(
+                    graphics as u32,
+                    async_compute_queue_family(&families),
+                ))
+            })
+        }
+    }
+
+    /// Whether FSR is dispatched on a queue of its own family, so that textures have to be
+    /// transferred between the families.
+    pub fn is_async(self) -> bool {
+        self.graphics != self.compute
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Transfer {
+    Release,
+    Acquire,
+}
+
+/// [`record_ownership_transfer`] into a wgpu command encoder, which is always of the graphics
+/// queue.
+///
+/// Textures are transferred in the layout wgpu uses for [`TextureUses::RESOURCE`], which they're
+/// transitioned to first when released, so that wgpu's own tracking agrees with them on both
+/// queues.
+pub(crate) fn transfer_ownership(
+    device: &Device,
+    command_encoder: &mut CommandEncoder,
+    textures: &[Fsr2Texture],
+    layer: u32,
+    families: (u32, u32),
+    transfer: Transfer,
+) {
+    unsafe {
+        if let Transfer::Release = transfer {
+            let transitions = textures
+                .iter()
+                .map(|texture| {
+                    (
+                        texture.texture,
+                        TextureUses::RESOURCE,
+                        selector(texture.texture, layer),
+                    )
+                })
+                .collect::<ArrayVec<_, 7>>();
+            command_encoder.transition_textures(&transitions);
+        }
+
+        let raw_device =
+            device.as_hal::<Vulkan, _, _>(|device| device.unwrap().raw_device().clone());
+        let command_buffer = command_encoder
+            .as_hal_mut::<Vulkan, _, _>(|cmd_encoder| cmd_encoder.unwrap().raw_handle());
+        record_ownership_transfer(
+            &raw_device,
+            command_buffer,
+            textures,
+            layer,
+            families,
+            transfer,
+        );
+    }
+}
+
+/// Record the `transfer` half of moving `textures` from the `src` queue family to the `dst` one
+/// into `command_buffer`, leaving them in the layout wgpu uses for [`TextureUses::RESOURCE`].
+///
+/// # Safety
+/// `command_buffer` must be recording, and the textures must already be in that layout.
+pub(crate) unsafe fn record_ownership_transfer(
+    raw_device: &ash::Device,
+    command_buffer: CommandBuffer,
+    textures: &[Fsr2Texture],
+    layer: u32,
+    (src, dst): (u32, u32),
+    transfer: Transfer,
+) {
+    let barriers = textures
+        .iter()
+        .map(|texture| {
+            let format = texture.texture.format();
+            let (layout, aspect_mask) = resource_layout(format);
+            let (src_access, dst_access) = match transfer {
+                Transfer::Release => (AccessFlags::MEMORY_WRITE, AccessFlags::empty()),
+                Transfer::Acquire => (
+                    AccessFlags::empty(),
+                    AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+                ),
+            };
+            ImageMemoryBarrier::builder()
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .old_layout(layout)
+                .new_layout(layout)
+                .src_queue_family_index(src)
+                .dst_queue_family_index(dst)
+                .image(
+                    texture
+                        .texture
+                        .as_hal::<Vulkan, _, _>(|texture| texture.unwrap().raw_handle()),
+                )
+                .subresource_range(
+                    ImageSubresourceRange::builder()
+                        .aspect_mask(aspect_mask)
+                        .base_mip_level(0)
+                        .level_count(1)
+                        .base_array_layer(selector(texture.texture, layer).layers.start)
+                        .layer_count(1)
+                        .build(),
+                )
+                .build()
+        })
+        .collect::<ArrayVec<_, 7>>();
+    let (src_stage, dst_stage) = match transfer {
+        Transfer::Release => (
+            PipelineStageFlags::ALL_COMMANDS,
+            PipelineStageFlags::BOTTOM_OF_PIPE,
+        ),
+        Transfer::Acquire => (
+            PipelineStageFlags::TOP_OF_PIPE,
+            PipelineStageFlags::ALL_COMMANDS,
+        ),
+    };
+
+    raw_device.cmd_pipeline_barrier(
+        command_buffer,
+        src_stage,
+        dst_stage,
+        DependencyFlags::empty(),
+        &[],
+        &[],
+        &barriers,
+    );
+}
+
+/// Textures without layers, like exposure, are shared by every layer.
+fn selector(texture: &wgpu::Texture, layer: u32) -> TextureSelector {
+    let layer = if texture.depth_or_array_layers() > 1 {
+        layer
+    } else {
+        0
+    };
+    TextureSelector {
+        mips: 0..1,
+        layers: layer..layer + 1,
+    }
+}
+
+/// The textures of `parameters` the caller renders or reads, which move between the queues.
+pub(crate) fn transferred_textures<'a>(
+    parameters: &Fsr2RenderParameters<'a>,
+    output: Fsr2Texture<'a>,
+) -> ArrayVec<Fsr2Texture<'a>, 7> {
+    let mut textures = ArrayVec::new();
+    textures.push(parameters.color);
+    textures.push(parameters.depth);
+    textures.push(parameters.motion_vectors);
+    if let Fsr2Exposure::ManualExposure { exposure, .. } = parameters.exposure {
+        textures.push(exposure);
+    }
+    if let Fsr2ReactiveMask::ManualMask(mask) = parameters.reactive_mask {
+        textures.push(mask);
+    }
+    if let Some(mask) = parameters.transparency_and_composition_mask {
+        textures.push(mask);
+    }
+    textures.push(output);
+    textures
+}
+
+/// How wgpu lays out a texture of `format` for [`TextureUses::RESOURCE`].
+fn resource_layout(format: TextureFormat) -> (ImageLayout, ImageAspectFlags) {
+    match format {
+        TextureFormat::Depth24PlusStencil8 | TextureFormat::Depth32FloatStencil8 => (
+            ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            ImageAspectFlags::DEPTH | ImageAspectFlags::STENCIL,
+        ),
+        _ if is_depth_format(format) => (
+            ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            ImageAspectFlags::DEPTH,
+        ),
+        _ => (
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ImageAspectFlags::COLOR,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_families_fall_back_to_graphics() {
+        let fallback = Fsr2QueueFamilies::new(0, None);
+        assert_eq!(fallback.compute, 0);
+        assert!(!fallback.is_async());
+
+        let queue_families = Fsr2QueueFamilies::new(0, Some(2));
+        assert_eq!(queue_families.compute, 2);
+        assert!(queue_families.is_async());
+    }
+}
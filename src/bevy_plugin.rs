@@ -0,0 +1,609 @@
+//! Upscaling of Bevy 3D cameras with FSR2.
+//!
+//! Cameras opt in by adding [`Fsr2Settings`] together with Bevy's `DepthPrepass`, and must have
+//! `hdr` enabled and [`Msaa::Off`]. The plugin then renders them at the input resolution of the
+//! chosen quality mode, jitters their projection, and upscales the result between the main pass
+//! and tonemapping.
+//!
+//! Bevy has no motion vector prepass yet, so motion vectors are reconstructed from depth and the
+//! camera's movement alone. Objects moving on their own will ghost. The camera's viewport is
+//! overridden with the input resolution region, so cameras with a custom viewport aren't
+//! supported either.
+//!
+//! Bevy has no way to set the mip bias of every sampler, so it's left to the application, through
+//! [`Fsr2CameraInfo::mip_bias`].
+
+use crate::blit::Blitter;
+use crate::{
+    Fsr2Context, Fsr2ContextDescriptor, Fsr2Error, Fsr2Exposure, Fsr2InitializationFlags,
+    Fsr2QualityMode, Fsr2ReactiveMask, Fsr2RenderParameters, Fsr2Texture,
+};
+use bevy::core_pipeline::core_3d;
+use bevy::core_pipeline::prepass::{DepthPrepass, ViewPrepassTextures};
+use bevy::log::{error, warn};
+use bevy::math::{Mat4, UVec2, UVec4, Vec2};
+use bevy::prelude::*;
+use bevy::render::camera::{CameraUpdateSystem, ExtractedCamera, Viewport};
+use bevy::render::render_graph::{
+    Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType,
+};
+use bevy::render::renderer::{RenderAdapter, RenderContext, RenderDevice};
+use bevy::render::view::{ExtractedView, ViewSet, ViewTarget};
+use bevy::render::{Extract, ExtractSchedule, RenderApp, RenderSet};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Mutex;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferUsages,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device, Extent3d,
+    FilterMode, PipelineLayoutDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+    StorageTextureAccess, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+const COLOR_FORMAT: TextureFormat = ViewTarget::TEXTURE_FORMAT_HDR;
+const MOTION_VECTOR_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+
+pub struct Fsr2Plugin;
+
+impl Plugin for Fsr2Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            update_camera_info
+                .in_base_set(CoreSet::PostUpdate)
+                .after(CameraUpdateSystem),
+        );
+
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<Fsr2Views>()
+            .init_resource::<Fsr2Pipelines>()
+            .add_system(extract_cameras.in_schedule(ExtractSchedule))
+            .add_system(
+                prepare_views
+                    .in_set(RenderSet::Prepare)
+                    .before(ViewSet::PrepareUniforms),
+            );
+
+        let node = Fsr2Node::new(&mut render_app.world);
+        let mut graph = render_app.world.resource_mut::<RenderGraph>();
+        let core_3d_graph = graph.get_sub_graph_mut(core_3d::graph::NAME).unwrap();
+        core_3d_graph.add_node(Fsr2Node::NAME, node);
+        let input_node_id = core_3d_graph.input_node().id;
+        core_3d_graph.add_slot_edge(
+            input_node_id,
+            core_3d::graph::input::VIEW_ENTITY,
+            Fsr2Node::NAME,
+            Fsr2Node::IN_VIEW,
+        );
+        core_3d_graph.add_node_edge(core_3d::graph::node::MAIN_PASS, Fsr2Node::NAME);
+        core_3d_graph.add_node_edge(Fsr2Node::NAME, core_3d::graph::node::TONEMAPPING);
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Fsr2Settings {
+    pub quality_mode: Fsr2QualityMode,
+    pub sharpness: f32,
+    /// Discard the accumulated history for the next frame, e.g. on a camera cut.
+    pub reset: bool,
+}
+
+impl Default for Fsr2Settings {
+    fn default() -> Self {
+        Self {
+            quality_mode: Fsr2QualityMode::Quality,
+            sharpness: 0.0,
+            reset: false,
+        }
+    }
+}
+
+/// Added by [`Fsr2Plugin`] to cameras with [`Fsr2Settings`].
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct Fsr2CameraInfo {
+    pub input_resolution: UVec2,
+    /// The bias to apply when sampling textures with mipmaps, see
+    /// [`Fsr2Context::suggested_mip_bias`].
+    pub mip_bias: f32,
+}
+
+fn update_camera_info(
+    mut commands: Commands,
+    cameras: Query<(Entity, &Camera, &Fsr2Settings, Option<&Fsr2CameraInfo>)>,
+) {
+    for (entity, camera, settings, info) in &cameras {
+        let Some(upscaled_resolution) = camera.physical_viewport_size() else {
+            continue;
+        };
+        let input_resolution = from_glam_uvec2(
+            settings
+                .quality_mode
+                .input_resolution(to_glam_uvec2(upscaled_resolution)),
+        );
+        let new_info = Fsr2CameraInfo {
+            input_resolution,
+            mip_bias: (input_resolution.x as f32 / upscaled_resolution.x as f32).log2() - 1.0,
+        };
+        if info != Some(&new_info) {
+            commands.entity(entity).insert(new_info);
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+struct ExtractedFsr2 {
+    settings: Fsr2Settings,
+    input_resolution: UVec2,
+    camera_near: f32,
+    camera_fov_angle_vertical: f32,
+}
+
+fn extract_cameras(
+    mut commands: Commands,
+    cameras: Extract<
+        Query<(Entity, &Camera, &Projection, &Fsr2Settings, &Fsr2CameraInfo), With<DepthPrepass>>,
+    >,
+    msaa: Extract<Res<Msaa>>,
+    mut warned_about_msaa: Local<bool>,
+) {
+    for (entity, camera, projection, settings, info) in &cameras {
+        let Projection::Perspective(projection) = projection else {
+            continue;
+        };
+        if !camera.is_active {
+            continue;
+        }
+        // FSR is given the depth prepass texture, which is multisampled along with the main pass
+        if **msaa != Msaa::Off {
+            if !*warned_about_msaa {
+                warn!("FSR2 requires Msaa::Off, not upscaling cameras until it is set");
+                *warned_about_msaa = true;
+            }
+            continue;
+        }
+
+        commands.get_or_spawn(entity).insert(ExtractedFsr2 {
+            settings: *settings,
+            input_resolution: info.input_resolution,
+            camera_near: projection.near,
+            camera_fov_angle_vertical: projection.fov,
+        });
+    }
+}
+
+/// Lets a [`Fsr2Context`] share Bevy's device.
+struct BevyDevice(RenderDevice);
+
+impl Deref for BevyDevice {
+    type Target = Device;
+
+    fn deref(&self) -> &Device {
+        self.0.wgpu_device()
+    }
+}
+
+struct Fsr2View {
+    context: Fsr2Context<BevyDevice>,
+    input_resolution: UVec2,
+    color: Texture,
+    color_view: TextureView,
+    motion_vectors: Texture,
+    motion_vectors_view: TextureView,
+    frame_index: i32,
+    jitter_offset: Vec2,
+    view_projection: Mat4,
+    previous_view_projection: Mat4,
+    reset: bool,
+}
+
+/// Per view state, locked by the render graph node, which only gets shared access to the world.
+#[derive(Resource, Default)]
+struct Fsr2Views(Mutex<HashMap<Entity, Fsr2View>>);
+
+fn prepare_views(
+    device: Res<RenderDevice>,
+    adapter: Res<RenderAdapter>,
+    mut fsr2_views: ResMut<Fsr2Views>,
+    mut views: Query<(
+        Entity,
+        &mut ExtractedCamera,
+        &mut ExtractedView,
+        &ExtractedFsr2,
+    )>,
+) {
+    let fsr2_views = fsr2_views.0.get_mut().unwrap();
+    fsr2_views.retain(|entity, _| views.contains(*entity));
+
+    for (entity, mut camera, mut view, fsr2) in &mut views {
+        let Some(upscaled_resolution) = camera.physical_viewport_size else {
+            continue;
+        };
+        if !view.hdr {
+            continue;
+        }
+        let input_resolution = fsr2.input_resolution;
+
+        // Recreate everything when the window resizes or the quality mode changes
+        let outdated = fsr2_views.get(&entity).map_or(true, |fsr2_view| {
+            fsr2_view.context.upscaled_resolution() != to_glam_uvec2(upscaled_resolution)
+                || fsr2_view.input_resolution != input_resolution
+        });
+        if outdated {
+            fsr2_views.remove(&entity);
+            match create_view(&device, &adapter, input_resolution, upscaled_resolution) {
+                Ok(fsr2_view) => {
+                    fsr2_views.insert(entity, fsr2_view);
+                }
+                Err(error) => {
+                    error!("Failed to create an Fsr2Context: {error}");
+                    continue;
+                }
+            }
+        }
+        let fsr2_view = fsr2_views.get_mut(&entity).unwrap();
+
+        let view_projection = view.projection * view.transform.compute_matrix().inverse();
+        fsr2_view.previous_view_projection = if outdated {
+            view_projection
+        } else {
+            fsr2_view.view_projection
+        };
+        fsr2_view.view_projection = view_projection;
+        fsr2_view.reset = fsr2.settings.reset;
+
+        fsr2_view.frame_index = fsr2_view.frame_index.wrapping_add(1);
+        let mut projection = to_glam_mat4(view.projection);
+        fsr2_view.jitter_offset =
+            from_glam_vec2(fsr2_view.context.jitter_camera_projection_matrix(
+                &mut projection,
+                to_glam_uvec2(input_resolution),
+                fsr2_view.frame_index,
+            ));
+        view.projection = from_glam_mat4(projection);
+
+        // Render into the top left corner of the view's textures
+        view.viewport = UVec4::new(0, 0, input_resolution.x, input_resolution.y);
+        camera.viewport = Some(Viewport {
+            physical_position: UVec2::ZERO,
+            physical_size: input_resolution,
+            depth: 0.0..1.0,
+        });
+    }
+}
+
+fn create_view(
+    device: &RenderDevice,
+    adapter: &RenderAdapter,
+    input_resolution: UVec2,
+    upscaled_resolution: UVec2,
+) -> Result<Fsr2View, Fsr2Error> {
+    let context = Fsr2Context::new(
+        BevyDevice(device.clone()),
+        adapter,
+        Fsr2ContextDescriptor::new(
+            to_glam_uvec2(input_resolution),
+            to_glam_uvec2(upscaled_resolution),
+        )
+        .initialization_flags(
+            Fsr2InitializationFlags::INVERTED_DEPTH
+                | Fsr2InitializationFlags::INFINITE_DEPTH
+                | Fsr2InitializationFlags::HIGH_DYNAMIC_RANGE,
+        )
+        .managed_output_format(COLOR_FORMAT),
+    )?;
+
+    let create_texture = |label, format, usage| {
+        let texture = device.wgpu_device().create_texture(&TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width: input_resolution.x,
+                height: input_resolution.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: usage | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        (texture, view)
+    };
+    let (color, color_view) = create_texture(
+        "fsr2_bevy_color",
+        COLOR_FORMAT,
+        TextureUsages::RENDER_ATTACHMENT,
+    );
+    let (motion_vectors, motion_vectors_view) = create_texture(
+        "fsr2_bevy_motion_vectors",
+        MOTION_VECTOR_FORMAT,
+        TextureUsages::STORAGE_BINDING,
+    );
+
+    Ok(Fsr2View {
+        context,
+        input_resolution,
+        color,
+        color_view,
+        motion_vectors,
+        motion_vectors_view,
+        frame_index: 0,
+        jitter_offset: Vec2::ZERO,
+        view_projection: Mat4::IDENTITY,
+        previous_view_projection: Mat4::IDENTITY,
+        reset: false,
+    })
+}
+
+#[derive(Resource)]
+struct Fsr2Pipelines {
+    motion_vectors_bind_group_layout: BindGroupLayout,
+    motion_vectors_pipeline: ComputePipeline,
+    blitter: Mutex<Blitter>,
+}
+
+impl FromWorld for Fsr2Pipelines {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>().wgpu_device();
+
+        let motion_vectors_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("fsr2_bevy_motion_vectors_bind_group_layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Depth,
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::StorageTexture {
+                            access: StorageTextureAccess::WriteOnly,
+                            format: MOTION_VECTOR_FORMAT,
+                            view_dimension: TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("fsr2_bevy_motion_vectors_shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/camera_motion_vectors.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fsr2_bevy_motion_vectors_pipeline_layout"),
+            bind_group_layouts: &[&motion_vectors_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let motion_vectors_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("fsr2_bevy_motion_vectors_pipeline"),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: "camera_motion_vectors",
+        });
+
+        Self {
+            motion_vectors_bind_group_layout,
+            motion_vectors_pipeline,
+            blitter: Mutex::new(Blitter::new(device, FilterMode::Nearest)),
+        }
+    }
+}
+
+struct Fsr2Node {
+    views: QueryState<(
+        &'static ViewTarget,
+        &'static ViewPrepassTextures,
+        &'static ExtractedFsr2,
+    )>,
+}
+
+impl Fsr2Node {
+    const NAME: &'static str = "fsr2";
+    const IN_VIEW: &'static str = "view";
+
+    fn new(world: &mut World) -> Self {
+        Self {
+            views: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for Fsr2Node {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.views.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let entity = graph.get_input_entity(Self::IN_VIEW)?;
+        let Ok((target, prepass_textures, fsr2)) = self.views.get_manual(world, entity) else {
+            return Ok(());
+        };
+        let Some(depth) = &prepass_textures.depth else {
+            return Ok(());
+        };
+        let mut fsr2_views = world.resource::<Fsr2Views>().0.lock().unwrap();
+        let Some(fsr2_view) = fsr2_views.get_mut(&entity) else {
+            return Ok(());
+        };
+        let pipelines = world.resource::<Fsr2Pipelines>();
+        let mut blitter = pipelines.blitter.lock().unwrap();
+        let device = world.resource::<RenderDevice>().wgpu_device();
+        let command_encoder = render_context.command_encoder();
+
+        let input_resolution = fsr2_view.input_resolution;
+        let upscaled_resolution = fsr2_view.context.upscaled_resolution();
+        let post_process = target.post_process_write();
+
+        // Only the input resolution region of the main texture was rendered to
+        let input_uv_scale =
+            to_glam_uvec2(input_resolution).as_vec2() / upscaled_resolution.as_vec2();
+        blitter.blit(
+            device,
+            command_encoder,
+            post_process.source,
+            input_uv_scale,
+            &fsr2_view.color_view,
+            COLOR_FORMAT,
+        );
+
+        let mut parameters = Vec::with_capacity(144);
+        for value in fsr2_view.view_projection.inverse().to_cols_array() {
+            parameters.extend_from_slice(&value.to_ne_bytes());
+        }
+        for value in fsr2_view.previous_view_projection.to_cols_array() {
+            parameters.extend_from_slice(&value.to_ne_bytes());
+        }
+        parameters.extend_from_slice(&(input_resolution.x as f32).to_ne_bytes());
+        parameters.extend_from_slice(&(input_resolution.y as f32).to_ne_bytes());
+        parameters.extend_from_slice(&[0; 8]);
+        let parameters = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fsr2_bevy_motion_vectors_parameters"),
+            contents: &parameters,
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fsr2_bevy_motion_vectors_bind_group"),
+            layout: &pipelines.motion_vectors_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&depth.default_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&fsr2_view.motion_vectors_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: parameters.as_entire_binding(),
+                },
+            ],
+        });
+        {
+            let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("fsr2_bevy_motion_vectors"),
+            });
+            pass.set_pipeline(&pipelines.motion_vectors_pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let workgroups = (input_resolution + 7) / 8;
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+
+        let output = fsr2_view.context.render(
+            command_encoder,
+            Fsr2RenderParameters {
+                color: Fsr2Texture {
+                    texture: &fsr2_view.color,
+                    view: &fsr2_view.color_view,
+                },
+                depth: Fsr2Texture {
+                    texture: &depth.texture,
+                    view: &depth.default_view,
+                },
+                motion_vectors: Fsr2Texture {
+                    texture: &fsr2_view.motion_vectors,
+                    view: &fsr2_view.motion_vectors_view,
+                },
+                motion_vector_scale: Some(to_glam_uvec2(input_resolution).as_vec2()),
+                exposure: Fsr2Exposure::AutoExposure,
+                reactive_mask: Fsr2ReactiveMask::NoMask,
+                transparency_and_composition_mask: None,
+                output: None,
+                input_resolution: to_glam_uvec2(input_resolution),
+                input_offset: glam::UVec2::ZERO,
+                output_offset: glam::UVec2::ZERO,
+                sharpness: fsr2.settings.sharpness,
+                frame_delta_time: None,
+                reset: fsr2_view.reset,
+                camera_near: fsr2.camera_near,
+                camera_far: None,
+                camera_fov_angle_vertical: fsr2.camera_fov_angle_vertical,
+                camera_view: None,
+                jitter_offset: to_glam_vec2(fsr2_view.jitter_offset),
+            },
+        );
+        match output {
+            Ok(Some(output)) => blitter.blit(
+                device,
+                command_encoder,
+                output.view,
+                glam::Vec2::ONE,
+                post_process.destination,
+                target.main_texture_format(),
+            ),
+            Ok(None) => unreachable!("the context always has a managed output"),
+            Err(error) => {
+                error!("Failed to upscale with FSR2: {error}");
+                // The destination must still be written to, so stretch the input over it instead
+                blitter.blit(
+                    device,
+                    command_encoder,
+                    post_process.source,
+                    input_uv_scale,
+                    post_process.destination,
+                    target.main_texture_format(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Bevy and this crate depend on different versions of glam
+
+fn to_glam_uvec2(v: UVec2) -> glam::UVec2 {
+    glam::UVec2::new(v.x, v.y)
+}
+
+fn from_glam_uvec2(v: glam::UVec2) -> UVec2 {
+    UVec2::new(v.x, v.y)
+}
+
+fn to_glam_vec2(v: Vec2) -> glam::Vec2 {
+    glam::Vec2::new(v.x, v.y)
+}
+
+fn from_glam_vec2(v: glam::Vec2) -> Vec2 {
+    Vec2::new(v.x, v.y)
+}
+
+fn to_glam_mat4(m: Mat4) -> glam::Mat4 {
+    glam::Mat4::from_cols_array(&m.to_cols_array())
+}
+
+fn from_glam_mat4(m: glam::Mat4) -> Mat4 {
+    Mat4::from_cols_array(&m.to_cols_array())
+}
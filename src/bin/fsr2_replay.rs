@@ -0,0 +1,265 @@
+//! Replays a capture made with `Fsr2Context::start_capture` through a fresh context, and writes
+//! each frame's output to `{output_directory}/frame_{index}_output.raw` as tightly packed
+//! Rgba16Float texels.
+//!
+//! Usage: `fsr2_replay <capture_directory> <output_directory>`
+
+use fsr2_wgpu::{
+    CaptureManifest, CapturedFrame, Fsr2CaptureInput, Fsr2Context, Fsr2ContextDescriptor,
+    Fsr2Exposure, Fsr2ExposureMode, Fsr2ReactiveMask, Fsr2RenderParameters, Fsr2Texture,
+};
+use glam::UVec2;
+use std::collections::HashMap;
+use std::error::Error;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::mpsc;
+use std::{env, fs};
+use wgpu::{
+    Backends, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, DeviceDescriptor,
+    Extent3d, Features, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, Instance,
+    InstanceDescriptor, Limits, Maintain, MapMode, Origin3d, PowerPreference, Queue,
+    RequestAdapterOptions, Texture, TextureAspect, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+const OUTPUT_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = env::args().collect::<Vec<_>>();
+    let [_, capture_directory, output_directory] = args.as_slice() else {
+        return Err("Usage: fsr2_replay <capture_directory> <output_directory>".into());
+    };
+    let capture_directory = Path::new(capture_directory);
+    let output_directory = Path::new(output_directory);
+    fs::create_dir_all(output_directory)?;
+
+    let manifest = CaptureManifest::load(capture_directory)?;
+
+    let instance = Instance::new(InstanceDescriptor {
+        backends: Backends::VULKAN,
+        ..Default::default()
+    });
+    let adapter = pollster::block_on(instance.request_adapter(&RequestAdapterOptions {
+        power_preference: PowerPreference::default(),
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }))
+    .ok_or("No Vulkan adapter available")?;
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &DeviceDescriptor {
+            label: None,
+            features: Features::empty(),
+            limits: Limits::default(),
+        },
+        None,
+    ))?;
+
+    let mut context = Fsr2Context::new(
+        &device,
+        &adapter,
+        Fsr2ContextDescriptor::new(manifest.max_input_resolution, manifest.upscaled_resolution)
+            .initialization_flags(manifest.initialization_flags),
+    )?;
+
+    let output = create_texture(
+        &device,
+        manifest.upscaled_resolution.x,
+        manifest.upscaled_resolution.y,
+        OUTPUT_FORMAT,
+        TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+    );
+    let output_view = output.create_view(&TextureViewDescriptor::default());
+
+    for (frame_index, frame) in manifest.frames.iter().enumerate() {
+        let inputs = load_inputs(&device, &queue, capture_directory, frame)?;
+        let input = |input| {
+            inputs
+                .get(&input)
+                .map(|(texture, view)| Fsr2Texture { texture, view })
+        };
+        let missing_input = |input| format!("Frame {frame_index} is missing {input:?}");
+
+        let snapshot = frame.parameters;
+        let parameters = Fsr2RenderParameters {
+            color: input(Fsr2CaptureInput::Color)
+                .ok_or_else(|| missing_input(Fsr2CaptureInput::Color))?,
+            depth: input(Fsr2CaptureInput::Depth)
+                .ok_or_else(|| missing_input(Fsr2CaptureInput::Depth))?,
+            motion_vectors: input(Fsr2CaptureInput::MotionVectors)
+                .ok_or_else(|| missing_input(Fsr2CaptureInput::MotionVectors))?,
+            motion_vector_scale: snapshot.motion_vector_scale,
+            exposure: match snapshot.exposure {
+                Fsr2ExposureMode::AutoExposure => Fsr2Exposure::AutoExposure,
+                Fsr2ExposureMode::ManualExposure { pre_exposure } => Fsr2Exposure::ManualExposure {
+                    pre_exposure,
+                    exposure: input(Fsr2CaptureInput::Exposure)
+                        .ok_or_else(|| missing_input(Fsr2CaptureInput::Exposure))?,
+                },
+            },
+            reactive_mask: match input(Fsr2CaptureInput::ReactiveMask) {
+                Some(mask) => Fsr2ReactiveMask::ManualMask(mask),
+                None => Fsr2ReactiveMask::NoMask,
+            },
+            transparency_and_composition_mask: input(
+                Fsr2CaptureInput::TransparencyAndCompositionMask,
+            ),
+            output: Some(Fsr2Texture {
+                texture: &output,
+                view: &output_view,
+            }),
+            input_resolution: snapshot.input_resolution,
+            input_offset: UVec2::ZERO,
+            output_offset: UVec2::ZERO,
+            sharpness: snapshot.sharpness,
+            frame_delta_time: Some(snapshot.frame_delta_time),
+            reset: snapshot.reset,
+            camera_near: snapshot.camera_near,
+            camera_far: snapshot.camera_far,
+            camera_fov_angle_vertical: snapshot.camera_fov_angle_vertical,
+            camera_view: snapshot.camera_view,
+            jitter_offset: snapshot.jitter_offset,
+        };
+
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+        context.render(&mut command_encoder, parameters)?;
+        queue.submit([command_encoder.finish()]);
+
+        let data = read_texture(&device, &queue, &output, 8)?;
+        fs::write(
+            output_directory.join(format!("frame_{frame_index}_output.raw")),
+            data,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn load_inputs(
+    device: &Device,
+    queue: &Queue,
+    capture_directory: &Path,
+    frame: &CapturedFrame,
+) -> Result<HashMap<Fsr2CaptureInput, (Texture, TextureView)>, Box<dyn Error>> {
+    let mut inputs = HashMap::new();
+    for captured in &frame.textures {
+        let format = match captured
+            .format()
+            .ok_or("Unknown texture format in capture")?
+        {
+            // Depth textures can't be written to directly, but FSR is happy to read depth from a
+            // regular float texture
+            TextureFormat::Depth32Float => TextureFormat::R32Float,
+            format => format,
+        };
+        let data = fs::read(capture_directory.join(&captured.file))?;
+
+        let texture = create_texture(
+            device,
+            captured.size.x,
+            captured.size.y,
+            format,
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+        );
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            &data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(data.len() as u32 / captured.size.y),
+                rows_per_image: None,
+            },
+            Extent3d {
+                width: captured.size.x,
+                height: captured.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        inputs.insert(captured.input, (texture, view));
+    }
+    Ok(inputs)
+}
+
+fn create_texture(
+    device: &Device,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    usage: TextureUsages,
+) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: None,
+        size: Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    })
+}
+
+fn read_texture(
+    device: &Device,
+    queue: &Queue,
+    texture: &Texture,
+    bytes_per_texel: u32,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let bytes_per_row = texture.width() * bytes_per_texel;
+    let padded_bytes_per_row =
+        bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (padded_bytes_per_row * texture.height()) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut command_encoder =
+        device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    command_encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        Extent3d {
+            width: texture.width(),
+            height: texture.height(),
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit([command_encoder.finish()]);
+
+    let (sender, receiver) = mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(Maintain::Wait);
+    receiver.recv()??;
+
+    let data = slice
+        .get_mapped_range()
+        .chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..bytes_per_row as usize])
+        .copied()
+        .collect();
+    Ok(data)
+}
@@ -0,0 +1,169 @@
+use glam::Vec2;
+use std::collections::HashMap;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferUsages,
+    ColorTargetState, ColorWrites, CommandEncoder, Device, FilterMode, FragmentState, LoadOp,
+    MultisampleState, Operations, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, SamplerDescriptor, ShaderModule, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, TextureFormat, TextureSampleType, TextureView,
+    TextureViewDimension, VertexState,
+};
+
+/// Stretches the top left part of a texture over the whole of a render target.
+pub(crate) struct Blitter {
+    bind_group_layout: BindGroupLayout,
+    pipeline_layout: PipelineLayout,
+    shader: ShaderModule,
+    sampler: Sampler,
+    pipelines: HashMap<TextureFormat, RenderPipeline>,
+}
+
+impl Blitter {
+    pub fn new(device: &Device, filter: FilterMode) -> Self {
+        let filtering = filter == FilterMode::Linear;
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fsr2_blit_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float {
+                            filterable: filtering,
+                        },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(if filtering {
+                        SamplerBindingType::Filtering
+                    } else {
+                        SamplerBindingType::NonFiltering
+                    }),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fsr2_blit_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("fsr2_blit_shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/blit.wgsl").into()),
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("fsr2_blit_sampler"),
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline_layout,
+            shader,
+            sampler,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Draw the `[0, uv_scale]` region of `source` over all of `target`.
+    pub fn blit(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        source: &TextureView,
+        uv_scale: Vec2,
+        target: &TextureView,
+        target_format: TextureFormat,
+    ) {
+        let mut parameters = Vec::with_capacity(16);
+        parameters.extend_from_slice(&uv_scale.x.to_ne_bytes());
+        parameters.extend_from_slice(&uv_scale.y.to_ne_bytes());
+        parameters.extend_from_slice(&[0; 8]);
+        let parameters = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fsr2_blit_parameters"),
+            contents: &parameters,
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fsr2_blit_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(source),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: parameters.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline = self.pipelines.entry(target_format).or_insert_with(|| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("fsr2_blit_pipeline"),
+                layout: Some(&self.pipeline_layout),
+                vertex: VertexState {
+                    module: &self.shader,
+                    entry_point: "vertex",
+                    buffers: &[],
+                },
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                fragment: Some(FragmentState {
+                    module: &self.shader,
+                    entry_point: "fragment",
+                    targets: &[Some(ColorTargetState {
+                        format: target_format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+        });
+
+        let mut pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("fsr2_blit"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
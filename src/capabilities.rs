@@ -0,0 +1,329 @@
+use crate::format::FormatTable;
+use crate::fsr::{FfxDeviceCapabilities, FfxShaderModel_FFX_SHADER_MODEL_5_1};
+use crate::Fsr2Context;
+use ash::vk;
+use std::ffi::CStr;
+use std::fmt::Write;
+use wgpu::{Adapter, Backend, Device, Limits, TextureFormat};
+use wgpu_core::api::Vulkan;
+
+/// Formats of the textures FSR creates for itself, all of which it writes to as storage images.
+const INTERNAL_FORMATS: &[TextureFormat] = &[
+    TextureFormat::R8Unorm,
+    TextureFormat::R16Float,
+    TextureFormat::R32Float,
+    TextureFormat::R32Uint,
+    TextureFormat::Rg8Unorm,
+    TextureFormat::Rg16Float,
+    TextureFormat::Rg32Float,
+    TextureFormat::Rgba8Unorm,
+    TextureFormat::Rgba16Float,
+];
+
+/// Whether FSR can run on a device, and if not, why. See [`Fsr2Context::is_supported`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fsr2Capabilities {
+    pub backend: Backend,
+    /// The device's capabilities as seen by FSR, or `None` if they couldn't be queried, e.g.
+    /// because the device isn't a Vulkan one.
+    pub device: Option<Fsr2DeviceCapabilities>,
+    pub insufficient_limits: Vec<Fsr2InsufficientLimit>,
+    /// Formats FSR needs to write to, but the device can't use as storage images.
+    pub unsupported_storage_formats: Vec<TextureFormat>,
+    /// A queue family of the adapter that supports compute but not graphics, if it has one, to
+    /// dispatch FSR on with [`crate::Fsr2QueueFamilies::new`].
+    pub async_compute_queue_family: Option<u32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fsr2DeviceCapabilities {
+    /// Major and minor version of the highest shader model FSR may target.
+    pub shader_model: (u32, u32),
+    pub wave_lane_count_min: u32,
+    pub wave_lane_count_max: u32,
+    /// Whether FSR will use its faster, half precision shader variants.
+    pub fp16_supported: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fsr2InsufficientLimit {
+    pub name: &'static str,
+    pub required: u32,
+    pub available: u32,
+}
+
+impl Fsr2Capabilities {
+    pub fn is_supported(&self) -> bool {
+        self.backend == Backend::Vulkan
+            && self.device.is_some()
+            && self.insufficient_limits.is_empty()
+            && self.unsupported_storage_formats.is_empty()
+    }
+
+    /// A description of what stops FSR from running, if anything, e.g. to show to users.
+    pub fn unsupported_reason(&self) -> Option<String> {
+        if self.backend != Backend::Vulkan {
+            return Some(format!(
+                "FSR2 requires Vulkan, but the device uses {:?}",
+                self.backend
+            ));
+        }
+        if self.device.is_none() {
+            return Some("FSR2 could not query the device's capabilities".to_owned());
+        }
+
+        let mut reason = String::new();
+        for limit in &self.insufficient_limits {
+            let _ = write!(
+                reason,
+                "{}{} is {}, but FSR2 requires {}",
+                if reason.is_empty() { "" } else { "; " },
+                limit.name,
+                limit.available,
+                limit.required
+            );
+        }
+        if !self.unsupported_storage_formats.is_empty() {
+            let _ = write!(
+                reason,
+                "{}FSR2 requires storage image support for {:?}",
+                if reason.is_empty() { "" } else { "; " },
+                self.unsupported_storage_formats
+            );
+        }
+        (!reason.is_empty()).then_some(reason)
+    }
+}
+
+// An impl for a single device type, so that callers can leave it out
+impl<'a> Fsr2Context<&'a Device> {
+    /// Check whether FSR can run on `device` before creating a context, which would otherwise fail
+    /// with e.g. [`crate::Fsr2Error::NullDevice`] or [`crate::Fsr2Error::BackendApiError`].
+    pub fn is_supported(adapter: &Adapter, device: &Device) -> Fsr2Capabilities {
+        let backend = adapter.get_info().backend;
+        let insufficient_limits = insufficient_limits(&device.limits());
+        if backend != Backend::Vulkan {
+            return Fsr2Capabilities {
+                backend,
+                device: None,
+                insufficient_limits,
+                unsupported_storage_formats: Vec::new(),
+                async_compute_queue_family: None,
+            };
+        }
+
+        let formats = FormatTable::new(adapter);
+        unsafe {
+            device.as_hal::<Vulkan, _, _>(|hal_device| {
+                let hal_device = hal_device.unwrap();
+                let instance = hal_device.shared_instance().raw_instance();
+                let physical_device = hal_device.raw_physical_device();
+
+                let unsupported_storage_formats = INTERNAL_FORMATS
+                    .iter()
+                    .copied()
+                    .filter(|&format| {
+                        formats.get(format).map_or(true, |vk_format| {
+                            !instance
+                                .get_physical_device_format_properties(physical_device, vk_format)
+                                .optimal_tiling_features
+                                .contains(vk::FormatFeatureFlags::STORAGE_IMAGE)
+                        })
+                    })
+                    .collect();
+
+                let async_compute_queue_family = async_compute_queue_family(
+                    &instance.get_physical_device_queue_family_properties(physical_device),
+                );
+
+                let device_capabilities = query_device_capabilities(instance, physical_device).ok();
+
+                Fsr2Capabilities {
+                    backend,
+                    device: device_capabilities,
+                    insufficient_limits,
+                    unsupported_storage_formats,
+                    async_compute_queue_family,
+                }
+            })
+        }
+    }
+}
+
+/// Ask the device what FSR's Vulkan backend would, the way it does.
+///
+/// FSR's own `fpGetDeviceCapabilities` reads the device's extensions from its backend context,
+/// which only exists once a context has been created, so it can't be called up front.
+unsafe fn query_device_capabilities(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+) -> Result<Fsr2DeviceCapabilities, vk::Result> {
+    // There are no shader models in Vulkan, so FSR assumes the minimum
+    let mut capabilities = FfxDeviceCapabilities {
+        minimumSupportedShaderModel: FfxShaderModel_FFX_SHADER_MODEL_5_1,
+        waveLaneCountMin: 32,
+        waveLaneCountMax: 32,
+        fp16Supported: false,
+        raytracingSupported: false,
+    };
+
+    for extension in instance.enumerate_device_extension_properties(physical_device)? {
+        let name = CStr::from_ptr(extension.extension_name.as_ptr());
+        if name == vk::ExtSubgroupSizeControlFn::name() {
+            let mut subgroup_size_control =
+                vk::PhysicalDeviceSubgroupSizeControlProperties::default();
+            let mut properties =
+                vk::PhysicalDeviceProperties2::builder().push_next(&mut subgroup_size_control);
+            instance.get_physical_device_properties2(physical_device, &mut properties);
+            capabilities.waveLaneCountMin = subgroup_size_control.min_subgroup_size;
+            capabilities.waveLaneCountMax = subgroup_size_control.max_subgroup_size;
+        } else if name == vk::KhrShaderFloat16Int8Fn::name() {
+            let mut float16_int8 = vk::PhysicalDeviceShaderFloat16Int8Features::default();
+            let mut features = vk::PhysicalDeviceFeatures2::builder().push_next(&mut float16_int8);
+            instance.get_physical_device_features2(physical_device, &mut features);
+            capabilities.fp16Supported = float16_int8.shader_float16 == vk::TRUE;
+        } else if name == vk::KhrAccelerationStructureFn::name() {
+            capabilities.raytracingSupported = true;
+        }
+    }
+
+    Ok(device_capabilities(capabilities))
+}
+
+fn device_capabilities(capabilities: FfxDeviceCapabilities) -> Fsr2DeviceCapabilities {
+    // Shader models are listed in order, with 5.1 followed by 6.0 onwards
+    let shader_model = match capabilities.minimumSupportedShaderModel {
+        FfxShaderModel_FFX_SHADER_MODEL_5_1 => (5, 1),
+        shader_model => (6, shader_model - FfxShaderModel_FFX_SHADER_MODEL_5_1 - 1),
+    };
+
+    Fsr2DeviceCapabilities {
+        shader_model,
+        wave_lane_count_min: capabilities.waveLaneCountMin,
+        wave_lane_count_max: capabilities.waveLaneCountMax,
+        fp16_supported: capabilities.fp16Supported,
+    }
+}
+
+pub(crate) fn async_compute_queue_family(families: &[vk::QueueFamilyProperties]) -> Option<u32> {
+    families
+        .iter()
+        .position(|family| {
+            family.queue_count > 0
+                && family.queue_flags.contains(vk::QueueFlags::COMPUTE)
+                && !family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+        })
+        .map(|index| index as u32)
+}
+
+fn insufficient_limits(limits: &Limits) -> Vec<Fsr2InsufficientLimit> {
+    // FSR's luminance pyramid pass runs 256 threads wide
+    [
+        (
+            "max_compute_workgroup_size_x",
+            256,
+            limits.max_compute_workgroup_size_x,
+        ),
+        (
+            "max_compute_invocations_per_workgroup",
+            256,
+            limits.max_compute_invocations_per_workgroup,
+        ),
+    ]
+    .into_iter()
+    .filter(|&(_, required, available)| available < required)
+    .map(|(name, required, available)| Fsr2InsufficientLimit {
+        name,
+        required,
+        available,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsr::FfxShaderModel_FFX_SHADER_MODEL_6_5;
+
+    #[test]
+    fn shader_models_are_converted() {
+        let capabilities = device_capabilities(FfxDeviceCapabilities {
+            minimumSupportedShaderModel: FfxShaderModel_FFX_SHADER_MODEL_6_5,
+            waveLaneCountMin: 32,
+            waveLaneCountMax: 64,
+            fp16Supported: true,
+            raytracingSupported: false,
+        });
+        assert_eq!(capabilities.shader_model, (6, 5));
+        assert_eq!(capabilities.wave_lane_count_max, 64);
+
+        let capabilities = device_capabilities(FfxDeviceCapabilities {
+            minimumSupportedShaderModel: FfxShaderModel_FFX_SHADER_MODEL_5_1,
+            waveLaneCountMin: 64,
+            waveLaneCountMax: 64,
+            fp16Supported: false,
+            raytracingSupported: false,
+        });
+        assert_eq!(capabilities.shader_model, (5, 1));
+    }
+
+    #[test]
+    fn unsupported_reasons_are_listed() {
+        let limits = Limits {
+            max_compute_invocations_per_workgroup: 128,
+            ..Limits::default()
+        };
+        let capabilities = Fsr2Capabilities {
+            backend: Backend::Vulkan,
+            device: None,
+            insufficient_limits: insufficient_limits(&limits),
+            unsupported_storage_formats: vec![TextureFormat::R8Unorm],
+            async_compute_queue_family: None,
+        };
+        assert!(!capabilities.is_supported());
+
+        let capabilities = Fsr2Capabilities {
+            device: Some(device_capabilities(FfxDeviceCapabilities {
+                minimumSupportedShaderModel: FfxShaderModel_FFX_SHADER_MODEL_5_1,
+                waveLaneCountMin: 32,
+                waveLaneCountMax: 32,
+                fp16Supported: false,
+                raytracingSupported: false,
+            })),
+            ..capabilities
+        };
+        assert_eq!(
+            capabilities.unsupported_reason().unwrap(),
+            "max_compute_invocations_per_workgroup is 128, but FSR2 requires 256; FSR2 requires \
+             storage image support for [R8Unorm]"
+        );
+
+        let capabilities = Fsr2Capabilities {
+            insufficient_limits: insufficient_limits(&Limits::default()),
+            unsupported_storage_formats: Vec::new(),
+            ..capabilities
+        };
+        assert!(capabilities.is_supported());
+        assert_eq!(capabilities.unsupported_reason(), None);
+    }
+
+    #[test]
+    fn async_compute_queue_family_is_found() {
+        let family = |queue_flags| vk::QueueFamilyProperties {
+            queue_flags,
+            queue_count: 1,
+            ..Default::default()
+        };
+
+        let graphics = family(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE);
+        assert_eq!(async_compute_queue_family(&[graphics]), None);
+        assert_eq!(
+            async_compute_queue_family(&[
+                graphics,
+                family(vk::QueueFlags::TRANSFER),
+                family(vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER),
+            ]),
+            Some(2)
+        );
+    }
+}
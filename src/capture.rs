@@ -0,0 +1,331 @@
+//! Capture of the inputs given to FSR, for reproducing issues offline with the `fsr2_replay`
+//! binary.
+//!
+//! A capture directory contains a `manifest.json` holding a [`CaptureManifest`], and for every
+//! captured frame one `frame_{index}_{input}.raw` file per input texture. Raw files hold tightly
+//! packed texel data, row by row from the top left, in the format named by the manifest.
+
+use crate::format::{bytes_per_texel, parse_format};
+use crate::viewport::{array_layer, is_depth_format};
+use crate::{
+    Fsr2ContextDescriptor, Fsr2InitializationFlags, Fsr2RenderParametersSnapshot, Fsr2Texture,
+    Fsr2WgpuError,
+};
+use glam::UVec2;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use wgpu::{
+    Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoder, Device, Extent3d,
+    ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, Maintain, MapMode, Origin3d, TextureAspect,
+    TextureFormat, TextureUsages, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CaptureManifest {
+    pub max_input_resolution: UVec2,
+    pub upscaled_resolution: UVec2,
+    pub initialization_flags: Fsr2InitializationFlags,
+    pub frames: Vec<CapturedFrame>,
+}
+
+impl CaptureManifest {
+    pub fn load(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(directory.as_ref().join("manifest.json"))?;
+        Ok(serde_json::from_reader(io::BufReader::new(file))?)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CapturedFrame {
+    pub parameters: Fsr2RenderParametersSnapshot,
+    pub textures: Vec<CapturedTexture>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CapturedTexture {
+    pub input: Fsr2CaptureInput,
+    pub file: String,
+    pub size: UVec2,
+    pub format: String,
+}
+
+impl CapturedTexture {
+    pub fn format(&self) -> Option<TextureFormat> {
+        parse_format(&self.format)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Fsr2CaptureInput {
+    Color,
+    Depth,
+    MotionVectors,
+    Exposure,
+    ReactiveMask,
+    TransparencyAndCompositionMask,
+}
+
+impl Fsr2CaptureInput {
+    fn file_suffix(self) -> &'static str {
+        match self {
+            Self::Color => "color",
+            Self::Depth => "depth",
+            Self::MotionVectors => "motion_vectors",
+            Self::Exposure => "exposure",
+            Self::ReactiveMask => "reactive_mask",
+            Self::TransparencyAndCompositionMask => "transparency_and_composition_mask",
+        }
+    }
+}
+
+pub(crate) struct FrameCapture {
+    directory: PathBuf,
+    frames_remaining: u32,
+    manifest: CaptureManifest,
+    /// Recorded frames waiting to be read back, oldest first.
+    pending: VecDeque<PendingFrame>,
+}
+
+struct PendingFrame {
+    parameters: Fsr2RenderParametersSnapshot,
+    textures: Vec<PendingTexture>,
+    /// The results of mapping the textures' buffers so far, once mapping has been requested.
+    // Not a channel, as receivers aren't Sync, and contexts are
+    mapped: Option<Arc<Mutex<Vec<Result<(), BufferAsyncError>>>>>,
+}
+
+struct PendingTexture {
+    input: Fsr2CaptureInput,
+    buffer: Buffer,
+    size: UVec2,
+    format: TextureFormat,
+    bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl FrameCapture {
+    pub fn new(
+        directory: &Path,
+        frame_count: u32,
+        descriptor: &Fsr2ContextDescriptor,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(directory)?;
+
+        Ok(Self {
+            directory: directory.to_owned(),
+            frames_remaining: frame_count,
+            manifest: CaptureManifest {
+                max_input_resolution: descriptor.max_input_resolution,
+                upscaled_resolution: descriptor.upscaled_resolution,
+                initialization_flags: descriptor.initialization_flags,
+                frames: Vec::new(),
+            },
+            pending: VecDeque::new(),
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frames_remaining == 0 && self.pending.is_empty()
+    }
+
+    /// Record copies of this frame's inputs, from `layer` of any array textures, into readback
+    /// buffers.
+    pub fn record(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        parameters: Fsr2RenderParametersSnapshot,
+        inputs: &[(Fsr2CaptureInput, Fsr2Texture)],
+        layer: u32,
+    ) -> Result<(), Fsr2WgpuError> {
+        if self.frames_remaining == 0 {
+            return Ok(());
+        }
+
+        let mut textures = Vec::with_capacity(inputs.len());
+        for (input, Fsr2Texture { texture, .. }) in inputs {
+            let format = texture.format();
+            let bytes_per_texel = bytes_per_texel(format)
+                .filter(|_| texture.usage().contains(TextureUsages::COPY_SRC))
+                .ok_or(Fsr2WgpuError::UncapturableTexture(format))?;
+
+            let size = UVec2::new(texture.width(), texture.height());
+            let bytes_per_row = size.x * bytes_per_texel;
+            let padded_bytes_per_row =
+                bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+            let buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("fsr2_capture_readback"),
+                size: (padded_bytes_per_row * size.y) as u64,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            command_encoder.copy_texture_to_buffer(
+                ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: array_layer(texture, layer),
+                    },
+                    // Copies out of depth textures must name the depth aspect
+                    aspect: if is_depth_format(format) {
+                        TextureAspect::DepthOnly
+                    } else {
+                        TextureAspect::All
+                    },
+                },
+                ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            textures.push(PendingTexture {
+                input: *input,
+                buffer,
+                size,
+                format,
+                bytes_per_row,
+                padded_bytes_per_row,
+            });
+        }
+
+        self.pending.push_back(PendingFrame {
+            parameters,
+            textures,
+            mapped: None,
+        });
+        self.frames_remaining -= 1;
+
+        Ok(())
+    }
+
+    /// Write the recorded frames the GPU is finished with to disk, along with an updated manifest.
+    ///
+    /// The commands recorded by [`FrameCapture::record`] must have already been submitted. Unless
+    /// `wait` is set, frames still in use by the GPU are left to a later call rather than waited
+    /// on.
+    pub fn flush(&mut self, device: &Device, wait: bool) -> Result<(), Fsr2WgpuError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        for frame in &mut self.pending {
+            frame.map();
+        }
+        device.poll(if wait { Maintain::Wait } else { Maintain::Poll });
+
+        while let Some(frame) = self.pending.front() {
+            match frame.poll_mapped(wait) {
+                Ok(false) => break,
+                Ok(true) => {
+                    let frame = self.pending.pop_front().unwrap();
+                    self.write(frame)?;
+                }
+                Err(error) => {
+                    // Drop the frame, so that later frames can still be written
+                    self.pending.pop_front();
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, frame: PendingFrame) -> Result<(), Fsr2WgpuError> {
+        let frame_index = self.manifest.frames.len();
+        let mut textures = Vec::with_capacity(frame.textures.len());
+        for texture in frame.textures {
+            let mut data = Vec::with_capacity((texture.bytes_per_row * texture.size.y) as usize);
+            for row in texture
+                .buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks(texture.padded_bytes_per_row as usize)
+            {
+                data.extend_from_slice(&row[..texture.bytes_per_row as usize]);
+            }
+            texture.buffer.unmap();
+
+            let file = format!("frame_{frame_index}_{}.raw", texture.input.file_suffix());
+            fs::write(self.directory.join(&file), data)?;
+
+            textures.push(CapturedTexture {
+                input: texture.input,
+                file,
+                size: texture.size,
+                format: format!("{:?}", texture.format),
+            });
+        }
+
+        self.manifest.frames.push(CapturedFrame {
+            parameters: frame.parameters,
+            textures,
+        });
+        let manifest = BufWriter::new(File::create(self.directory.join("manifest.json"))?);
+        serde_json::to_writer_pretty(manifest, &self.manifest).map_err(io::Error::from)?;
+
+        Ok(())
+    }
+}
+
+impl PendingFrame {
+    /// Request that the frame's buffers be mapped, if not done already.
+    fn map(&mut self) {
+        if self.mapped.is_some() {
+            return;
+        }
+
+        let mapped = Arc::new(Mutex::new(Vec::with_capacity(self.textures.len())));
+        for texture in &self.textures {
+            let mapped = mapped.clone();
+            texture
+                .buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    mapped.lock().unwrap().push(result);
+                });
+        }
+        self.mapped = Some(mapped);
+    }
+
+    /// Whether every buffer of the frame has been mapped. If `wait` is set, the device has been
+    /// waited on, so buffers that still aren't mapped never will be.
+    fn poll_mapped(&self, wait: bool) -> Result<bool, Fsr2WgpuError> {
+        let Some(mapped) = &self.mapped else {
+            return Ok(false);
+        };
+
+        let mut mapped = mapped.lock().unwrap();
+        if let Some(index) = mapped.iter().position(Result::is_err) {
+            let error = mapped.swap_remove(index).unwrap_err();
+            return Err(io::Error::new(io::ErrorKind::Other, error).into());
+        }
+        if wait && mapped.len() < self.textures.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "capture readback buffers weren't mapped after waiting on the device",
+            )
+            .into());
+        }
+
+        Ok(mapped.len() == self.textures.len())
+    }
+}
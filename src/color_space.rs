@@ -0,0 +1,382 @@
+use crate::{Fsr2InitializationFlags, Fsr2Texture, Fsr2WgpuError};
+use glam::UVec2;
+use std::collections::HashMap;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferUsages,
+    ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d, FragmentState, LoadOp,
+    MultisampleState, Operations, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+const LINEAR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// How the color input and output are encoded.
+///
+/// FSR works on linear color, so encoded color spaces are decoded before FSR reads the color
+/// input, and FSR's output is encoded again before it's written to the output texture. Primaries
+/// are left as they are.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Fsr2ColorSpace {
+    /// Linear color in `[0, 1]`, in any float, unorm or sRGB format.
+    LinearSrgb,
+    /// Linear color where 1.0 is 80 nits, in `Rgba16Float`, `Rgba32Float` or `Rg11b10Float`.
+    ScRgb,
+    /// HDR10's PQ encoded color, in `Rgb10a2Unorm` or `Rgba16Float`. Decoded to scRGB's scale.
+    Hdr10Pq,
+    /// sRGB encoded color stored as is in `Rgba8Unorm` or `Bgra8Unorm`. Textures of `*Srgb`
+    /// formats are decoded when they're read, so those are [`Fsr2ColorSpace::LinearSrgb`].
+    Srgb,
+}
+
+impl Fsr2ColorSpace {
+    /// Whether this needs [`Fsr2InitializationFlags::HIGH_DYNAMIC_RANGE`].
+    pub fn is_hdr(self) -> bool {
+        matches!(self, Self::ScRgb | Self::Hdr10Pq)
+    }
+
+    pub fn is_encoded(self) -> bool {
+        matches!(self, Self::Hdr10Pq | Self::Srgb)
+    }
+
+    /// Must match the transfer function numbering of `color_space.wgsl`.
+    fn transfer(self) -> u32 {
+        match self {
+            Self::LinearSrgb | Self::ScRgb => 0,
+            Self::Srgb => 1,
+            Self::Hdr10Pq => 2,
+        }
+    }
+
+    /// Check that FSR is set up for this color space, and that `color_format` can hold it.
+    pub(crate) fn validate(
+        self,
+        initialization_flags: Fsr2InitializationFlags,
+        color_format: TextureFormat,
+    ) -> Result<(), Fsr2WgpuError> {
+        if initialization_flags.contains(Fsr2InitializationFlags::HIGH_DYNAMIC_RANGE)
+            != self.is_hdr()
+        {
+            return Err(Fsr2WgpuError::ColorSpaceDynamicRangeMismatch(self));
+        }
+
+        let format_matches = match self {
+            Self::LinearSrgb => matches!(
+                color_format.describe().sample_type,
+                TextureSampleType::Float { .. }
+            ),
+            Self::ScRgb => matches!(
+                color_format,
+                TextureFormat::Rgba16Float
+                    | TextureFormat::Rgba32Float
+                    | TextureFormat::Rg11b10Float
+            ),
+            Self::Hdr10Pq => matches!(
+                color_format,
+                TextureFormat::Rgb10a2Unorm | TextureFormat::Rgba16Float
+            ),
+            Self::Srgb => matches!(
+                color_format,
+                TextureFormat::Rgba8Unorm | TextureFormat::Bgra8Unorm
+            ),
+        };
+        if !format_matches {
+            return Err(Fsr2WgpuError::ColorSpaceFormatMismatch {
+                color_space: self,
+                format: color_format,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes the color input into a linear texture for FSR to read, and encodes the linear texture
+/// FSR writes to into the output.
+pub(crate) struct ColorSpaceConverter {
+    color_space: Fsr2ColorSpace,
+    bind_group_layout: BindGroupLayout,
+    pipeline_layout: PipelineLayout,
+    shader: ShaderModule,
+    decode_pipeline: RenderPipeline,
+    encode_pipelines: HashMap<TextureFormat, RenderPipeline>,
+    linear_input: (Texture, TextureView),
+    linear_output: (Texture, TextureView),
+}
+
+impl ColorSpaceConverter {
+    pub fn new(
+        device: &Device,
+        color_space: Fsr2ColorSpace,
+        max_input_resolution: UVec2,
+        upscaled_resolution: UVec2,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fsr2_color_space_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fsr2_color_space_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("fsr2_color_space_shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/color_space.wgsl").into()),
+        });
+        let decode_pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            "fsr2_color_space_decode_pipeline",
+            "decode",
+            LINEAR_FORMAT,
+        );
+
+        let create_texture = |label, size: UVec2, usage| {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: LINEAR_FORMAT,
+                usage: usage | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            (texture, view)
+        };
+
+        Self {
+            color_space,
+            linear_input: create_texture(
+                "fsr2_linear_input",
+                max_input_resolution,
+                TextureUsages::RENDER_ATTACHMENT,
+            ),
+            linear_output: create_texture(
+                "fsr2_linear_output",
+                upscaled_resolution,
+                TextureUsages::STORAGE_BINDING,
+            ),
+            bind_group_layout,
+            pipeline_layout,
+            shader,
+            decode_pipeline,
+            encode_pipelines: HashMap::new(),
+        }
+    }
+
+    /// Decode the `input_resolution` sized top left region of `color`.
+    pub fn decode(
+        &self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        color: &Fsr2Texture,
+        input_resolution: UVec2,
+    ) -> Fsr2Texture<'_> {
+        let (texture, view) = &self.linear_input;
+        self.draw(
+            device,
+            command_encoder,
+            &self.decode_pipeline,
+            color.view,
+            view,
+            input_resolution,
+        );
+        Fsr2Texture { texture, view }
+    }
+
+    /// The texture for FSR to write to, to be encoded with [`ColorSpaceConverter::encode`].
+    pub fn linear_output(&self) -> Fsr2Texture<'_> {
+        let (texture, view) = &self.linear_output;
+        Fsr2Texture { texture, view }
+    }
+
+    /// Encode all of [`ColorSpaceConverter::linear_output`] into `output`, which needs
+    /// [`TextureUsages::RENDER_ATTACHMENT`].
+    pub fn encode(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        output: &Fsr2Texture,
+    ) {
+        let format = output.texture.format();
+        if !self.encode_pipelines.contains_key(&format) {
+            let pipeline = create_pipeline(
+                device,
+                &self.pipeline_layout,
+                &self.shader,
+                "fsr2_color_space_encode_pipeline",
+                "encode",
+                format,
+            );
+            self.encode_pipelines.insert(format, pipeline);
+        }
+
+        let (linear_output, linear_output_view) = &self.linear_output;
+        self.draw(
+            device,
+            command_encoder,
+            &self.encode_pipelines[&format],
+            linear_output_view,
+            output.view,
+            UVec2::new(linear_output.width(), linear_output.height()),
+        );
+    }
+
+    fn draw(
+        &self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        source: &TextureView,
+        target: &TextureView,
+        size: UVec2,
+    ) {
+        let mut parameters = Vec::with_capacity(16);
+        parameters.extend_from_slice(&self.color_space.transfer().to_ne_bytes());
+        parameters.extend_from_slice(&[0; 12]);
+        let parameters = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fsr2_color_space_parameters"),
+            contents: &parameters,
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fsr2_color_space_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(source),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: parameters.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("fsr2_color_space"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_viewport(0.0, 0.0, size.x as f32, size.y as f32, 0.0, 1.0);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &Device,
+    layout: &PipelineLayout,
+    shader: &ShaderModule,
+    label: &str,
+    entry_point: &str,
+    format: TextureFormat,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: VertexState {
+            module: shader,
+            entry_point: "vertex",
+            buffers: &[],
+        },
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module: shader,
+            entry_point,
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_formats_are_validated() {
+        let hdr = Fsr2InitializationFlags::HIGH_DYNAMIC_RANGE;
+        let sdr = Fsr2InitializationFlags::empty();
+
+        assert!(Fsr2ColorSpace::LinearSrgb
+            .validate(sdr, TextureFormat::Rgba8UnormSrgb)
+            .is_ok());
+        assert!(Fsr2ColorSpace::ScRgb
+            .validate(hdr, TextureFormat::Rgba16Float)
+            .is_ok());
+        assert!(Fsr2ColorSpace::Hdr10Pq
+            .validate(hdr, TextureFormat::Rgb10a2Unorm)
+            .is_ok());
+        assert!(Fsr2ColorSpace::Srgb
+            .validate(sdr, TextureFormat::Bgra8Unorm)
+            .is_ok());
+
+        assert!(matches!(
+            Fsr2ColorSpace::ScRgb.validate(sdr, TextureFormat::Rgba16Float),
+            Err(Fsr2WgpuError::ColorSpaceDynamicRangeMismatch(
+                Fsr2ColorSpace::ScRgb
+            ))
+        ));
+        assert!(matches!(
+            Fsr2ColorSpace::Srgb.validate(sdr, TextureFormat::Rgba8UnormSrgb),
+            Err(Fsr2WgpuError::ColorSpaceFormatMismatch {
+                color_space: Fsr2ColorSpace::Srgb,
+                format: TextureFormat::Rgba8UnormSrgb,
+            })
+        ));
+        assert!(matches!(
+            Fsr2ColorSpace::LinearSrgb.validate(sdr, TextureFormat::R32Uint),
+            Err(Fsr2WgpuError::ColorSpaceFormatMismatch { .. })
+        ));
+    }
+}
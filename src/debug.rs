@@ -0,0 +1,183 @@
+use crate::{Fsr2DebugResource, Fsr2Texture};
+use glam::UVec2;
+use std::collections::HashMap;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferUsages, Color,
+    ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d, FragmentState, LoadOp,
+    MultisampleState, Operations, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+/// Owns copies of FSR's internal resources, and the pipelines used to visualize them.
+pub(crate) struct DebugVisualizer {
+    textures: HashMap<Fsr2DebugResource, (Texture, TextureView)>,
+    bind_group_layout: BindGroupLayout,
+    pipeline_layout: PipelineLayout,
+    shader: ShaderModule,
+    pipelines: HashMap<TextureFormat, RenderPipeline>,
+}
+
+impl DebugVisualizer {
+    pub fn new(device: &Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fsr2_debug_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fsr2_debug_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("fsr2_debug_shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/debug_visualize.wgsl").into()),
+        });
+
+        Self {
+            textures: HashMap::new(),
+            bind_group_layout,
+            pipeline_layout,
+            shader,
+            pipelines: HashMap::new(),
+        }
+    }
+
+    pub fn texture(
+        &mut self,
+        device: &Device,
+        resource: Fsr2DebugResource,
+        size: UVec2,
+    ) -> Fsr2Texture<'_> {
+        let (texture, view) = self.textures.entry(resource).or_insert_with(|| {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some("fsr2_debug_resource"),
+                size: Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: resource.format(),
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            (texture, view)
+        });
+
+        Fsr2Texture { texture, view }
+    }
+
+    pub fn visualize(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        resource: Fsr2DebugResource,
+        valid_size: UVec2,
+        target: &TextureView,
+        target_format: TextureFormat,
+    ) {
+        let (_, view) = &self.textures[&resource];
+
+        let mode: u32 = match resource {
+            Fsr2DebugResource::DilatedMotionVectors => 0,
+            Fsr2DebugResource::DilatedDepth => 1,
+            Fsr2DebugResource::PreparedInputColor | Fsr2DebugResource::InternalUpscaledColor => 2,
+            _ => 3,
+        };
+        let mut parameters = Vec::with_capacity(16);
+        parameters.extend_from_slice(&(valid_size.x as f32).to_ne_bytes());
+        parameters.extend_from_slice(&(valid_size.y as f32).to_ne_bytes());
+        parameters.extend_from_slice(&mode.to_ne_bytes());
+        parameters.extend_from_slice(&0u32.to_ne_bytes());
+        let parameters = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fsr2_debug_parameters"),
+            contents: &parameters,
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fsr2_debug_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: parameters.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline = self.pipelines.entry(target_format).or_insert_with(|| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("fsr2_debug_pipeline"),
+                layout: Some(&self.pipeline_layout),
+                vertex: VertexState {
+                    module: &self.shader,
+                    entry_point: "vertex",
+                    buffers: &[],
+                },
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                fragment: Some(FragmentState {
+                    module: &self.shader,
+                    entry_point: "fragment",
+                    targets: &[Some(ColorTargetState {
+                        format: target_format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+        });
+
+        let mut pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("fsr2_debug_visualize"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,372 @@
+use crate::format::FormatTable;
+use crate::{
+    Fsr2Allocation, Fsr2AllocationCallbacks, Fsr2Context, Fsr2ContextDescriptor, Fsr2Error,
+};
+use ash::vk;
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_char, CStr};
+use std::mem;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use wgpu::{Adapter, Device};
+use wgpu_core::api::Vulkan;
+use wgpu_hal::DeviceError;
+
+/// Creates [`Fsr2Context`]s that share a pipeline cache.
+///
+/// FSR's Vulkan backend builds its own pipelines and layouts for every context, so they can't be
+/// handed out directly. Instead, contexts created through the same `Fsr2Device` build their
+/// pipelines through a shared Vulkan pipeline cache, so only the first context pays for shader
+/// compilation.
+///
+/// wgpu has no pipeline cache of its own to hook into, but the cache's contents can be saved with
+/// [`Fsr2Device::pipeline_cache_data`] and restored with [`Fsr2Device::with_pipeline_cache_data`]
+/// to also skip compilation in later runs.
+pub struct Fsr2Device<D: Deref<Target = Device> + Clone> {
+    device: D,
+    formats: FormatTable,
+    pipeline_cache: vk::PipelineCache,
+    allocation_callbacks: Option<Arc<dyn Fsr2AllocationCallbacks>>,
+}
+
+impl<D: Deref<Target = Device> + Clone> Fsr2Device<D> {
+    pub fn new(device: D, adapter: &Adapter) -> Result<Self, DeviceError> {
+        Self::with_pipeline_cache_data(device, adapter, &[])
+    }
+
+    /// Data that isn't from a compatible driver and device is ignored.
+    pub fn with_pipeline_cache_data(
+        device: D,
+        adapter: &Adapter,
+        pipeline_cache_data: &[u8],
+    ) -> Result<Self, DeviceError> {
+        let pipeline_cache = unsafe {
+            device.as_hal::<Vulkan, _, _>(|hal_device| {
+                let hal_device = hal_device.unwrap();
+                register_device(
+                    hal_device.raw_device().handle(),
+                    hal_device
+                        .shared_instance()
+                        .raw_instance()
+                        .fp_v1_0()
+                        .get_device_proc_addr,
+                );
+
+                let create_info =
+                    vk::PipelineCacheCreateInfo::builder().initial_data(pipeline_cache_data);
+                hal_device
+                    .raw_device()
+                    .create_pipeline_cache(&create_info, None)
+                    .map_err(map_vk_error)
+            })?
+        };
+
+        Ok(Self {
+            formats: FormatTable::new(adapter),
+            device,
+            pipeline_cache,
+            allocation_callbacks: None,
+        })
+    }
+
+    /// Callbacks told about the device memory allocations of contexts created from now on.
+    pub fn set_allocation_callbacks(&mut self, callbacks: impl Fsr2AllocationCallbacks + 'static) {
+        self.allocation_callbacks = Some(Arc::new(callbacks));
+    }
+
+    pub fn create_context(
+        &self,
+        descriptor: Fsr2ContextDescriptor,
+    ) -> Result<Fsr2Context<D>, Fsr2Error> {
+        let _hooks = CreationHooks::set(self.pipeline_cache, self.allocation_callbacks.clone());
+        unsafe {
+            Fsr2Context::create(
+                self.device.clone(),
+                self.formats.clone(),
+                descriptor,
+                Some(get_device_proc_addr),
+            )
+        }
+    }
+
+    /// The pipeline cache's contents, to be given to [`Fsr2Device::with_pipeline_cache_data`].
+    pub fn pipeline_cache_data(&self) -> Result<Vec<u8>, DeviceError> {
+        unsafe {
+            self.device.as_hal::<Vulkan, _, _>(|hal_device| {
+                hal_device
+                    .unwrap()
+                    .raw_device()
+                    .get_pipeline_cache_data(self.pipeline_cache)
+                    .map_err(map_vk_error)
+            })
+        }
+    }
+}
+
+impl<D: Deref<Target = Device> + Clone> Drop for Fsr2Device<D> {
+    fn drop(&mut self) {
+        // Contexts only use the cache while they're being created, so they may outlive it
+        unsafe {
+            self.device.as_hal::<Vulkan, _, _>(|hal_device| {
+                hal_device
+                    .unwrap()
+                    .raw_device()
+                    .destroy_pipeline_cache(self.pipeline_cache, None);
+            });
+        }
+    }
+}
+
+fn map_vk_error(error: vk::Result) -> DeviceError {
+    match error {
+        vk::Result::ERROR_OUT_OF_HOST_MEMORY | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+            DeviceError::OutOfMemory
+        }
+        _ => DeviceError::Lost,
+    }
+}
+
+// FSR loads vkCreateComputePipelines through the vkGetDeviceProcAddr it's given, and always
+// passes it a null pipeline cache. Both are swapped out below, so that pipelines are created with
+// the cache of the Fsr2Device creating the context. The callbacks have no user data, so the cache
+// is passed through a thread local, set for the duration of context creation.
+//
+// vkAllocateMemory and vkFreeMemory are swapped out the same way, to call the device's
+// allocation callbacks. FSR only allocates while the context is created, but frees when it's
+// destroyed, so accepted allocations are recorded along with the callbacks to free them with.
+
+thread_local! {
+    static PIPELINE_CACHE: Cell<vk::PipelineCache> = Cell::new(vk::PipelineCache::null());
+    static ALLOCATION_CALLBACKS: RefCell<Option<Arc<dyn Fsr2AllocationCallbacks>>> =
+        RefCell::new(None);
+}
+
+/// Sets the thread locals for the context being created, and clears them when dropped, even if
+/// creating the context panics.
+struct CreationHooks;
+
+impl CreationHooks {
+    fn set(
+        pipeline_cache: vk::PipelineCache,
+        allocation_callbacks: Option<Arc<dyn Fsr2AllocationCallbacks>>,
+    ) -> Self {
+        PIPELINE_CACHE.with(|cache| cache.set(pipeline_cache));
+        ALLOCATION_CALLBACKS.with(|callbacks| *callbacks.borrow_mut() = allocation_callbacks);
+        Self
+    }
+}
+
+impl Drop for CreationHooks {
+    fn drop(&mut self) {
+        PIPELINE_CACHE.with(|cache| cache.set(vk::PipelineCache::null()));
+        ALLOCATION_CALLBACKS.with(|callbacks| *callbacks.borrow_mut() = None);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct DeviceFunctions {
+    device: vk::Device,
+    get_device_proc_addr: vk::PFN_vkGetDeviceProcAddr,
+    create_compute_pipelines: Option<vk::PFN_vkCreateComputePipelines>,
+    allocate_memory: Option<vk::PFN_vkAllocateMemory>,
+    free_memory: Option<vk::PFN_vkFreeMemory>,
+}
+
+static DEVICE_FUNCTIONS: Mutex<Vec<DeviceFunctions>> = Mutex::new(Vec::new());
+
+struct TrackedAllocation {
+    device: vk::Device,
+    memory: vk::DeviceMemory,
+    allocation: Fsr2Allocation,
+    callbacks: Arc<dyn Fsr2AllocationCallbacks>,
+}
+
+static ALLOCATIONS: Mutex<Vec<TrackedAllocation>> = Mutex::new(Vec::new());
+
+fn register_device(device: vk::Device, get_device_proc_addr: vk::PFN_vkGetDeviceProcAddr) {
+    let mut device_functions = DEVICE_FUNCTIONS.lock().unwrap();
+    if let Some(functions) = device_functions
+        .iter()
+        .find(|functions| functions.device == device)
+    {
+        // Earlier contexts of the device still call through these hooks, so clearing them
+        // would leave those calls without the functions they forward to
+        if functions.get_device_proc_addr as usize == get_device_proc_addr as usize {
+            return;
+        }
+    }
+    // Handles of destroyed devices may be reused
+    device_functions.retain(|functions| functions.device != device);
+    device_functions.push(DeviceFunctions {
+        device,
+        get_device_proc_addr,
+        create_compute_pipelines: None,
+        allocate_memory: None,
+        free_memory: None,
+    });
+}
+
+fn device_functions(device: vk::Device) -> Option<DeviceFunctions> {
+    DEVICE_FUNCTIONS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|functions| functions.device == device)
+        .copied()
+}
+
+unsafe extern "system" fn get_device_proc_addr(
+    device: vk::Device,
+    name: *const c_char,
+) -> vk::PFN_vkVoidFunction {
+    let Some(functions) = device_functions(device) else {
+        return None;
+    };
+    let function = (functions.get_device_proc_addr)(device, name)?;
+
+    let mut device_functions = DEVICE_FUNCTIONS.lock().unwrap();
+    let Some(functions) = device_functions
+        .iter_mut()
+        .find(|functions| functions.device == device)
+    else {
+        return Some(function);
+    };
+    match CStr::from_ptr(name).to_bytes() {
+        b"vkCreateComputePipelines" => {
+            functions.create_compute_pipelines = Some(mem::transmute(function));
+            Some(mem::transmute(
+                create_compute_pipelines as vk::PFN_vkCreateComputePipelines,
+            ))
+        }
+        b"vkAllocateMemory" => {
+            functions.allocate_memory = Some(mem::transmute(function));
+            Some(mem::transmute(allocate_memory as vk::PFN_vkAllocateMemory))
+        }
+        b"vkFreeMemory" => {
+            functions.free_memory = Some(mem::transmute(function));
+            Some(mem::transmute(free_memory as vk::PFN_vkFreeMemory))
+        }
+        _ => Some(function),
+    }
+}
+
+unsafe extern "system" fn create_compute_pipelines(
+    device: vk::Device,
+    pipeline_cache: vk::PipelineCache,
+    create_info_count: u32,
+    create_infos: *const vk::ComputePipelineCreateInfo,
+    allocator: *const vk::AllocationCallbacks,
+    pipelines: *mut vk::Pipeline,
+) -> vk::Result {
+    let Some(create_compute_pipelines) =
+        device_functions(device).and_then(|functions| functions.create_compute_pipelines)
+    else {
+        return vk::Result::ERROR_INITIALIZATION_FAILED;
+    };
+
+    let pipeline_cache = if pipeline_cache == vk::PipelineCache::null() {
+        PIPELINE_CACHE.with(Cell::get)
+    } else {
+        pipeline_cache
+    };
+    create_compute_pipelines(
+        device,
+        pipeline_cache,
+        create_info_count,
+        create_infos,
+        allocator,
+        pipelines,
+    )
+}
+
+unsafe extern "system" fn allocate_memory(
+    device: vk::Device,
+    allocate_info: *const vk::MemoryAllocateInfo,
+    allocator: *const vk::AllocationCallbacks,
+    memory: *mut vk::DeviceMemory,
+) -> vk::Result {
+    let Some(allocate_memory) =
+        device_functions(device).and_then(|functions| functions.allocate_memory)
+    else {
+        return vk::Result::ERROR_INITIALIZATION_FAILED;
+    };
+    let Some(callbacks) = ALLOCATION_CALLBACKS.with(|callbacks| callbacks.borrow().clone()) else {
+        return allocate_memory(device, allocate_info, allocator, memory);
+    };
+
+    let allocation = Fsr2Allocation {
+        size: (*allocate_info).allocation_size,
+        memory_type_index: (*allocate_info).memory_type_index,
+    };
+    if !callbacks.allocate(&allocation) {
+        return vk::Result::ERROR_OUT_OF_DEVICE_MEMORY;
+    }
+
+    let result = allocate_memory(device, allocate_info, allocator, memory);
+    if result == vk::Result::SUCCESS {
+        ALLOCATIONS.lock().unwrap().push(TrackedAllocation {
+            device,
+            memory: *memory,
+            allocation,
+            callbacks,
+        });
+    } else {
+        callbacks.free(&allocation);
+    }
+    result
+}
+
+unsafe extern "system" fn free_memory(
+    device: vk::Device,
+    memory: vk::DeviceMemory,
+    allocator: *const vk::AllocationCallbacks,
+) {
+    let Some(free_memory) = device_functions(device).and_then(|functions| functions.free_memory)
+    else {
+        return;
+    };
+    free_memory(device, memory, allocator);
+
+    let tracked = {
+        let mut allocations = ALLOCATIONS.lock().unwrap();
+        allocations
+            .iter()
+            .position(|tracked| tracked.device == device && tracked.memory == memory)
+            .map(|index| allocations.swap_remove(index))
+    };
+    if let Some(tracked) = tracked {
+        tracked.callbacks.free(&tracked.allocation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+    use std::panic;
+
+    struct AcceptAll;
+
+    impl Fsr2AllocationCallbacks for AcceptAll {
+        fn allocate(&self, _: &Fsr2Allocation) -> bool {
+            true
+        }
+
+        fn free(&self, _: &Fsr2Allocation) {}
+    }
+
+    #[test]
+    fn creation_hooks_are_cleared_when_creation_panics() {
+        let pipeline_cache = vk::PipelineCache::from_raw(1);
+        let result = panic::catch_unwind(|| {
+            let _hooks = CreationHooks::set(pipeline_cache, Some(Arc::new(AcceptAll)));
+            assert_eq!(PIPELINE_CACHE.with(Cell::get), pipeline_cache);
+            assert!(ALLOCATION_CALLBACKS.with(|callbacks| callbacks.borrow().is_some()));
+            panic!("context creation panicked");
+        });
+
+        assert!(result.is_err());
+        assert_eq!(PIPELINE_CACHE.with(Cell::get), vk::PipelineCache::null());
+        assert!(ALLOCATION_CALLBACKS.with(|callbacks| callbacks.borrow().is_none()));
+    }
+}
@@ -0,0 +1,142 @@
+use ash::vk::Format;
+use std::collections::HashMap;
+use wgpu::{Adapter, TextureFormat};
+use wgpu_core::api::Vulkan;
+
+/// Every uncompressed color and depth format, so that any texture FSR can read or write has its
+/// Vulkan format looked up, even when it's an unusual choice for an input.
+const FORMATS: &[TextureFormat] = &[
+    TextureFormat::R8Unorm,
+    TextureFormat::R8Snorm,
+    TextureFormat::R8Uint,
+    TextureFormat::R8Sint,
+    TextureFormat::R16Uint,
+    TextureFormat::R16Sint,
+    TextureFormat::R16Unorm,
+    TextureFormat::R16Snorm,
+    TextureFormat::R16Float,
+    TextureFormat::Rg8Unorm,
+    TextureFormat::Rg8Snorm,
+    TextureFormat::Rg8Uint,
+    TextureFormat::Rg8Sint,
+    TextureFormat::R32Uint,
+    TextureFormat::R32Sint,
+    TextureFormat::R32Float,
+    TextureFormat::Rg16Uint,
+    TextureFormat::Rg16Sint,
+    TextureFormat::Rg16Unorm,
+    TextureFormat::Rg16Snorm,
+    TextureFormat::Rg16Float,
+    TextureFormat::Rgba8Unorm,
+    TextureFormat::Rgba8UnormSrgb,
+    TextureFormat::Rgba8Snorm,
+    TextureFormat::Rgba8Uint,
+    TextureFormat::Rgba8Sint,
+    TextureFormat::Bgra8Unorm,
+    TextureFormat::Bgra8UnormSrgb,
+    TextureFormat::Rgb9e5Ufloat,
+    TextureFormat::Rgb10a2Unorm,
+    TextureFormat::Rg11b10Float,
+    TextureFormat::Rg32Uint,
+    TextureFormat::Rg32Sint,
+    TextureFormat::Rg32Float,
+    TextureFormat::Rgba16Uint,
+    TextureFormat::Rgba16Sint,
+    TextureFormat::Rgba16Unorm,
+    TextureFormat::Rgba16Snorm,
+    TextureFormat::Rgba16Float,
+    TextureFormat::Rgba32Uint,
+    TextureFormat::Rgba32Sint,
+    TextureFormat::Rgba32Float,
+    TextureFormat::Depth16Unorm,
+    TextureFormat::Depth24Plus,
+    TextureFormat::Depth24PlusStencil8,
+    TextureFormat::Depth32Float,
+    TextureFormat::Depth32FloatStencil8,
+];
+
+/// wgpu to Vulkan texture format mappings, looked up from the adapter once at context creation.
+#[derive(Clone)]
+pub(crate) struct FormatTable {
+    formats: HashMap<TextureFormat, Format>,
+}
+
+impl FormatTable {
+    pub fn new(adapter: &Adapter) -> Self {
+        let formats = FORMATS
+            .iter()
+            .filter_map(|&format| unsafe {
+                adapter
+                    .texture_format_as_hal::<Vulkan>(format)
+                    .map(|vk_format| (format, vk_format))
+            })
+            .collect();
+
+        Self { formats }
+    }
+
+    pub fn get(&self, format: TextureFormat) -> Option<Format> {
+        self.formats.get(&format).copied()
+    }
+}
+
+/// Size of a single texel, for formats that can be copied to a buffer in their entirety.
+#[cfg(feature = "capture")]
+pub(crate) fn bytes_per_texel(format: TextureFormat) -> Option<u32> {
+    match format {
+        TextureFormat::R8Unorm
+        | TextureFormat::R8Snorm
+        | TextureFormat::R8Uint
+        | TextureFormat::R8Sint => Some(1),
+        TextureFormat::R16Uint
+        | TextureFormat::R16Sint
+        | TextureFormat::R16Unorm
+        | TextureFormat::R16Snorm
+        | TextureFormat::R16Float
+        | TextureFormat::Rg8Unorm
+        | TextureFormat::Rg8Snorm
+        | TextureFormat::Rg8Uint
+        | TextureFormat::Rg8Sint
+        | TextureFormat::Depth16Unorm => Some(2),
+        TextureFormat::R32Uint
+        | TextureFormat::R32Sint
+        | TextureFormat::R32Float
+        | TextureFormat::Rg16Uint
+        | TextureFormat::Rg16Sint
+        | TextureFormat::Rg16Unorm
+        | TextureFormat::Rg16Snorm
+        | TextureFormat::Rg16Float
+        | TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Rgba8Snorm
+        | TextureFormat::Rgba8Uint
+        | TextureFormat::Rgba8Sint
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb
+        | TextureFormat::Rgb9e5Ufloat
+        | TextureFormat::Rgb10a2Unorm
+        | TextureFormat::Rg11b10Float
+        | TextureFormat::Depth32Float => Some(4),
+        TextureFormat::Rg32Uint
+        | TextureFormat::Rg32Sint
+        | TextureFormat::Rg32Float
+        | TextureFormat::Rgba16Uint
+        | TextureFormat::Rgba16Sint
+        | TextureFormat::Rgba16Unorm
+        | TextureFormat::Rgba16Snorm
+        | TextureFormat::Rgba16Float => Some(8),
+        TextureFormat::Rgba32Uint | TextureFormat::Rgba32Sint | TextureFormat::Rgba32Float => {
+            Some(16)
+        }
+        _ => None,
+    }
+}
+
+/// Parse a format from its [`Debug`] representation, as stored in capture manifests.
+#[cfg(feature = "capture")]
+pub(crate) fn parse_format(name: &str) -> Option<TextureFormat> {
+    FORMATS
+        .iter()
+        .copied()
+        .find(|format| format!("{format:?}") == name)
+}
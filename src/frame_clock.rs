@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+/// The longest measured frame delta time given to FSR, so that a long pause (a breakpoint, a
+/// minimized window, a loading screen) doesn't throw off its exposure adaptation.
+pub(crate) const MAX_FRAME_DELTA_TIME: Duration = Duration::from_millis(100);
+
+/// The frame delta time handed to FSR: the caller's if provided, otherwise the measured one
+/// clamped to [`MAX_FRAME_DELTA_TIME`].
+pub(crate) fn frame_delta_time(provided: Option<Duration>, measured: Duration) -> Duration {
+    provided.unwrap_or(measured.min(MAX_FRAME_DELTA_TIME))
+}
+
+/// Measures the time between frames, for callers that don't provide a frame delta time.
+#[derive(Clone)]
+pub(crate) struct FrameClock {
+    last_frame: Option<Instant>,
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        Self { last_frame: None }
+    }
+
+    /// Start a new frame, returning the time since the previous one. Pass the result through
+    /// [`frame_delta_time`] before handing it to FSR.
+    pub fn tick(&mut self) -> Duration {
+        self.tick_at(Instant::now())
+    }
+
+    fn tick_at(&mut self, now: Instant) -> Duration {
+        let delta_time = match self.last_frame {
+            Some(last_frame) => now.saturating_duration_since(last_frame),
+            None => Duration::ZERO,
+        };
+        self.last_frame = Some(now);
+
+        delta_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_measures_time_since_previous_tick() {
+        let start = Instant::now();
+        let mut clock = FrameClock::new();
+
+        assert_eq!(clock.tick_at(start), Duration::ZERO);
+
+        let frame = start + Duration::from_micros(4167);
+        assert_eq!(clock.tick_at(frame), Duration::from_micros(4167));
+
+        let after_pause = frame + Duration::from_secs(30);
+        assert_eq!(clock.tick_at(after_pause), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn measured_frame_delta_time_is_clamped() {
+        let pause = Duration::from_secs(30);
+        assert_eq!(frame_delta_time(None, pause), MAX_FRAME_DELTA_TIME);
+
+        let frame = Duration::from_micros(4167);
+        assert_eq!(frame_delta_time(None, frame), frame);
+
+        // The caller's frame delta time is trusted as is
+        assert_eq!(frame_delta_time(Some(pause), frame), pause);
+    }
+}
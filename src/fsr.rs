@@ -10,11 +10,13 @@ type VkPhysicalDevice = ash::vk::PhysicalDevice;
 type VkDevice = ash::vk::Device;
 type VkImage = ash::vk::Image;
 type VkImageView = ash::vk::ImageView;
+type VkImageLayout = ash::vk::ImageLayout;
 type VkFormat = ash::vk::Format;
 type VkCommandBuffer = ash::vk::CommandBuffer;
 type PFN_vkGetDeviceProcAddr = ash::vk::PFN_vkGetDeviceProcAddr;
 
 bitflags::bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
     pub struct Fsr2InitializationFlags: FfxFsr2InitializationFlagBits {
         const AUTO_EXPOSURE = FfxFsr2InitializationFlagBits_FFX_FSR2_ENABLE_AUTO_EXPOSURE;
         const INFINITE_DEPTH = FfxFsr2InitializationFlagBits_FFX_FSR2_ENABLE_DEPTH_INFINITE;
@@ -33,6 +35,48 @@ pub enum Fsr2WgpuError {
     Fsr2(#[from] Fsr2Error),
     #[error(transparent)]
     Wgpu(#[from] wgpu_hal::DeviceError),
+    #[error("Multisampled input texture given without MSAA resolve enabled")]
+    MultisampledInput,
+    #[error("Fsr2Context was not created with Fsr2InitializationFlags::AUTO_EXPOSURE")]
+    AutoExposureDisabled,
+    #[error("No output texture given without managed output enabled")]
+    MissingOutput,
+    #[error("Texture format {0:?} can not be used with FSR")]
+    UnsupportedFormat(wgpu::TextureFormat),
+    #[error(
+        "Texture of format {0:?} can not be captured, it must be copyable and have COPY_SRC usage"
+    )]
+    UncapturableTexture(wgpu::TextureFormat),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Multisampled input textures can not be read at an offset")]
+    MultisampledInputAtOffset,
+    #[error("Input or output region does not fit within its texture")]
+    RegionOutOfBounds,
+    #[error("Expected render parameters for {expected} views, got {actual}")]
+    ViewCountMismatch { expected: usize, actual: usize },
+    #[error("Color texture format {format:?} can not hold {color_space:?} color")]
+    ColorSpaceFormatMismatch {
+        color_space: crate::Fsr2ColorSpace,
+        format: wgpu::TextureFormat,
+    },
+    #[error(
+        "Fsr2InitializationFlags::HIGH_DYNAMIC_RANGE must be set if and only if the color space, \
+         {0:?}, is HDR"
+    )]
+    ColorSpaceDynamicRangeMismatch(crate::Fsr2ColorSpace),
+    #[error(
+        "With async compute, FSR is recorded into a compute command buffer with \
+         Fsr2Context::render_on_compute, not into a wgpu command encoder"
+    )]
+    AsyncComputeEncoder,
+    #[error(
+        "MSAA resolves, input and output offsets, color space conversion and capture need the \
+         graphics queue, so can't be done with async compute"
+    )]
+    AsyncComputeUnsupported,
+    #[error("FSR's resources can't be read before the first frame has been dispatched")]
+    NotYetDispatched,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -92,6 +136,7 @@ pub fn ffx_check_result(result: FfxErrorCode) -> Result<(), Fsr2Error> {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Fsr2QualityMode {
     Native,
     Quality,
@@ -100,11 +145,80 @@ pub enum Fsr2QualityMode {
     UltraPerformance,
 }
 
+impl Fsr2QualityMode {
+    /// The input resolution suggested for upscaling to `upscaled_resolution` in this mode.
+    pub(crate) fn input_resolution(self, upscaled_resolution: glam::UVec2) -> glam::UVec2 {
+        let scale_factor = match self {
+            Self::Native => 1.0,
+            Self::Quality => 1.5,
+            Self::Balanced => 1.7,
+            Self::Performance => 2.0,
+            Self::UltraPerformance => 3.0,
+        };
+
+        (upscaled_resolution.as_vec2() / scale_factor).as_uvec2()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Fsr2DebugResource {
+    DilatedMotionVectors,
+    DilatedDepth,
+    LockStatus,
+    DepthClip,
+    PreparedInputColor,
+    InternalUpscaledColor,
+    LumaHistory,
+    DilatedReactiveMasks,
+    AutoExposure,
+}
+
+impl Fsr2DebugResource {
+    pub(crate) fn resource_id(self) -> u32 {
+        match self {
+            Self::DilatedMotionVectors => FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_MOTION_VECTORS,
+            Self::DilatedDepth => FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_DEPTH,
+            Self::LockStatus => FFX_FSR2_RESOURCE_IDENTIFIER_LOCK_STATUS,
+            Self::DepthClip => FFX_FSR2_RESOURCE_IDENTIFIER_DEPTH_CLIP,
+            Self::PreparedInputColor => FFX_FSR2_RESOURCE_IDENTIFIER_PREPARED_INPUT_COLOR,
+            Self::InternalUpscaledColor => FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_UPSCALED_COLOR,
+            Self::LumaHistory => FFX_FSR2_RESOURCE_IDENTIFIER_LUMA_HISTORY,
+            Self::DilatedReactiveMasks => FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_REACTIVE_MASKS,
+            Self::AutoExposure => FFX_FSR2_RESOURCE_IDENTIFIER_AUTO_EXPOSURE,
+        }
+    }
+
+    /// Must match the format FSR creates the resource with, as it is copied out with
+    /// vkCmdCopyImage.
+    pub(crate) fn format(self) -> wgpu::TextureFormat {
+        match self {
+            Self::DilatedMotionVectors => wgpu::TextureFormat::Rg16Float,
+            Self::DilatedDepth => wgpu::TextureFormat::R32Float,
+            Self::LockStatus => wgpu::TextureFormat::Rg16Float,
+            Self::DepthClip => wgpu::TextureFormat::R8Unorm,
+            Self::PreparedInputColor => wgpu::TextureFormat::Rgba16Float,
+            Self::InternalUpscaledColor => wgpu::TextureFormat::Rgba16Float,
+            Self::LumaHistory => wgpu::TextureFormat::Rgba8Unorm,
+            Self::DilatedReactiveMasks => wgpu::TextureFormat::Rg8Unorm,
+            Self::AutoExposure => wgpu::TextureFormat::Rg32Float,
+        }
+    }
+
+    pub(crate) fn is_display_resolution(self) -> bool {
+        matches!(
+            self,
+            Self::LockStatus | Self::InternalUpscaledColor | Self::LumaHistory
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct Fsr2Texture<'a> {
     pub texture: &'a wgpu::Texture,
     pub view: &'a wgpu::TextureView,
 }
 
+#[derive(Clone, Copy, Debug)]
 pub enum Fsr2Exposure<'a> {
     AutoExposure,
     ManualExposure {
@@ -113,6 +227,7 @@ pub enum Fsr2Exposure<'a> {
     },
 }
 
+#[derive(Clone, Copy, Debug)]
 pub enum Fsr2ReactiveMask<'a> {
     NoMask,
     ManualMask(Fsr2Texture<'a>),
@@ -127,6 +242,7 @@ pub enum Fsr2ReactiveMask<'a> {
 }
 
 bitflags::bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
     pub struct Fsr2AutoGenerateReactiveMaskFlags: u32 {
       const ApplyTonemap = FFX_FSR2_AUTOREACTIVEFLAGS_APPLY_TONEMAP;
       const ApplyInverseTonemap = FFX_FSR2_AUTOREACTIVEFLAGS_APPLY_INVERSETONEMAP;
@@ -0,0 +1,183 @@
+use crate::{Fsr2QualityMode, Fsr2Texture, Fsr2WgpuError, RcasPass, Upscaler, UpscalerInputs};
+use glam::{UVec2, Vec2};
+use std::ops::Deref;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferUsages,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    Extent3d, PipelineLayoutDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+    StorageTextureAccess, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+const UPSCALED_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// FSR 1.0 spatial upscaling (EASU followed by RCAS), for when temporal upscaling isn't an option.
+///
+/// Only color is used, so depth and motion vectors may be any texture. Color is best given in a
+/// perceptual space, i.e. after tonemapping. The output has the same requirements as
+/// [`RcasPass`]'s.
+pub struct Fsr1Upscaler<D: Deref<Target = Device>> {
+    device: D,
+    upscaled_resolution: UVec2,
+    easu_bind_group_layout: BindGroupLayout,
+    easu_pipeline: ComputePipeline,
+    upscaled: Texture,
+    upscaled_view: TextureView,
+    rcas: RcasPass,
+}
+
+impl<D: Deref<Target = Device>> Fsr1Upscaler<D> {
+    pub fn new(device: D, upscaled_resolution: UVec2) -> Self {
+        let easu_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fsr1_easu_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: UPSCALED_FORMAT,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("fsr1_easu_shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/fsr1_easu.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fsr1_easu_pipeline_layout"),
+            bind_group_layouts: &[&easu_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let easu_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("fsr1_easu_pipeline"),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: "easu",
+        });
+
+        let upscaled = device.create_texture(&TextureDescriptor {
+            label: Some("fsr1_upscaled"),
+            size: Extent3d {
+                width: upscaled_resolution.x,
+                height: upscaled_resolution.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: UPSCALED_FORMAT,
+            usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        Self {
+            upscaled_view: upscaled.create_view(&TextureViewDescriptor::default()),
+            upscaled,
+            device,
+            upscaled_resolution,
+            easu_bind_group_layout,
+            easu_pipeline,
+            rcas: RcasPass::new(),
+        }
+    }
+
+    pub fn upscaled_resolution(&self) -> UVec2 {
+        self.upscaled_resolution
+    }
+}
+
+impl<D: Deref<Target = Device>> Upscaler for Fsr1Upscaler<D> {
+    fn suggested_input_resolution(&self, quality_mode: Fsr2QualityMode) -> UVec2 {
+        quality_mode.input_resolution(self.upscaled_resolution)
+    }
+
+    fn jitter(&self, _input_resolution: UVec2, _frame_index: i32) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn mip_bias(&self, input_resolution: UVec2) -> f32 {
+        (input_resolution.x as f32 / self.upscaled_resolution.x as f32).log2()
+    }
+
+    fn record(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        inputs: UpscalerInputs,
+    ) -> Result<(), Fsr2WgpuError> {
+        let mut parameters = Vec::with_capacity(16);
+        parameters.extend_from_slice(&(inputs.input_resolution.x as f32).to_ne_bytes());
+        parameters.extend_from_slice(&(inputs.input_resolution.y as f32).to_ne_bytes());
+        parameters.extend_from_slice(&[0; 8]);
+        let parameters = self.device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fsr1_easu_parameters"),
+            contents: &parameters,
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fsr1_easu_bind_group"),
+            layout: &self.easu_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(inputs.color.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&self.upscaled_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: parameters.as_entire_binding(),
+                },
+            ],
+        });
+
+        {
+            let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("fsr1_easu"),
+            });
+            pass.set_pipeline(&self.easu_pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let workgroups = (self.upscaled_resolution + 7) / 8;
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+
+        self.rcas.record(
+            &self.device,
+            command_encoder,
+            Fsr2Texture {
+                texture: &self.upscaled,
+                view: &self.upscaled_view,
+            },
+            inputs.output,
+            inputs.sharpness,
+        )
+    }
+}
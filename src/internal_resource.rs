@@ -0,0 +1,235 @@
+use crate::fsr::{ffxGetVkImage, ffxGetVkImageLayout, FfxFsr2Context};
+use ash::vk::{
+    AccessFlags, Buffer, BufferImageCopy, BufferMemoryBarrier, CommandBuffer, DependencyFlags,
+    Extent3D, Image, ImageAspectFlags, ImageCopy, ImageLayout, ImageMemoryBarrier,
+    ImageSubresourceLayers, ImageSubresourceRange, Offset3D, PipelineStageFlags,
+    QUEUE_FAMILY_IGNORED, WHOLE_SIZE,
+};
+use glam::UVec2;
+
+/// Records a copy of one of FSR's internal images into a Vulkan buffer.
+///
+/// FSR tracks the layout of its internal images itself, so the image is transitioned to
+/// TRANSFER_SRC_OPTIMAL for the copy, and then back to whatever layout FSR left it in.
+///
+/// wgpu doesn't track the write, so the buffer is synchronized with barriers of its own: earlier
+/// accesses finish before the copy, and the copy is visible to later commands and the host.
+pub(crate) unsafe fn copy_internal_image_to_buffer(
+    context: &mut FfxFsr2Context,
+    device: &ash::Device,
+    command_buffer: CommandBuffer,
+    resource_id: u32,
+    size: UVec2,
+    buffer: Buffer,
+    buffer_offset: u64,
+) {
+    let image = ffxGetVkImage(context as *mut _, resource_id);
+    let layout = ffxGetVkImageLayout(context as *mut _, resource_id);
+
+    transition_internal_image(
+        device,
+        command_buffer,
+        image,
+        (
+            layout,
+            AccessFlags::SHADER_WRITE,
+            PipelineStageFlags::COMPUTE_SHADER,
+        ),
+        (
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            AccessFlags::TRANSFER_READ,
+            PipelineStageFlags::TRANSFER,
+        ),
+    );
+
+    buffer_barrier(
+        device,
+        command_buffer,
+        buffer,
+        (
+            AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE,
+            PipelineStageFlags::ALL_COMMANDS,
+        ),
+        (AccessFlags::TRANSFER_WRITE, PipelineStageFlags::TRANSFER),
+    );
+
+    device.cmd_copy_image_to_buffer(
+        command_buffer,
+        image,
+        ImageLayout::TRANSFER_SRC_OPTIMAL,
+        buffer,
+        &[BufferImageCopy::builder()
+            .buffer_offset(buffer_offset)
+            .image_subresource(color_subresource_layers())
+            .image_offset(Offset3D::default())
+            .image_extent(Extent3D {
+                width: size.x,
+                height: size.y,
+                depth: 1,
+            })
+            .build()],
+    );
+
+    buffer_barrier(
+        device,
+        command_buffer,
+        buffer,
+        (AccessFlags::TRANSFER_WRITE, PipelineStageFlags::TRANSFER),
+        (
+            AccessFlags::MEMORY_READ | AccessFlags::MEMORY_WRITE | AccessFlags::HOST_READ,
+            PipelineStageFlags::ALL_COMMANDS | PipelineStageFlags::HOST,
+        ),
+    );
+
+    transition_internal_image(
+        device,
+        command_buffer,
+        image,
+        (
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            AccessFlags::TRANSFER_READ,
+            PipelineStageFlags::TRANSFER,
+        ),
+        (
+            layout,
+            AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE,
+            PipelineStageFlags::COMPUTE_SHADER,
+        ),
+    );
+}
+
+pub(crate) unsafe fn transition_internal_image(
+    device: &ash::Device,
+    command_buffer: CommandBuffer,
+    image: Image,
+    (old_layout, src_access, src_stage): (ImageLayout, AccessFlags, PipelineStageFlags),
+    (new_layout, dst_access, dst_stage): (ImageLayout, AccessFlags, PipelineStageFlags),
+) {
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        src_stage,
+        dst_stage,
+        DependencyFlags::empty(),
+        &[],
+        &[],
+        &[ImageMemoryBarrier::builder()
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(
+                ImageSubresourceRange::builder()
+                    .aspect_mask(ImageAspectFlags::COLOR)
+                    .base_mip_level(0)
+                    .level_count(1)
+                    .base_array_layer(0)
+                    .layer_count(1)
+                    .build(),
+            )
+            .build()],
+    );
+}
+
+unsafe fn buffer_barrier(
+    device: &ash::Device,
+    command_buffer: CommandBuffer,
+    buffer: Buffer,
+    (src_access, src_stage): (AccessFlags, PipelineStageFlags),
+    (dst_access, dst_stage): (AccessFlags, PipelineStageFlags),
+) {
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        src_stage,
+        dst_stage,
+        DependencyFlags::empty(),
+        &[],
+        &[BufferMemoryBarrier::builder()
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access)
+            .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .buffer(buffer)
+            .offset(0)
+            .size(WHOLE_SIZE)
+            .build()],
+        &[],
+    );
+}
+
+fn color_subresource_layers() -> ImageSubresourceLayers {
+    ImageSubresourceLayers::builder()
+        .aspect_mask(ImageAspectFlags::COLOR)
+        .mip_level(0)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build()
+}
+
+/// Records a copy of one of FSR's internal images into another Vulkan image of the same format.
+///
+/// The destination image must already be in TRANSFER_DST_OPTIMAL.
+pub(crate) unsafe fn copy_internal_image_to_image(
+    context: &mut FfxFsr2Context,
+    device: &ash::Device,
+    command_buffer: CommandBuffer,
+    resource_id: u32,
+    size: UVec2,
+    destination: Image,
+) {
+    let image = ffxGetVkImage(context as *mut _, resource_id);
+    let layout = ffxGetVkImageLayout(context as *mut _, resource_id);
+
+    transition_internal_image(
+        device,
+        command_buffer,
+        image,
+        (
+            layout,
+            AccessFlags::SHADER_WRITE,
+            PipelineStageFlags::COMPUTE_SHADER,
+        ),
+        (
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            AccessFlags::TRANSFER_READ,
+            PipelineStageFlags::TRANSFER,
+        ),
+    );
+
+    device.cmd_copy_image(
+        command_buffer,
+        image,
+        ImageLayout::TRANSFER_SRC_OPTIMAL,
+        destination,
+        ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[ImageCopy::builder()
+            .src_subresource(color_subresource_layers())
+            .src_offset(Offset3D::default())
+            .dst_subresource(color_subresource_layers())
+            .dst_offset(Offset3D::default())
+            .extent(Extent3D {
+                width: size.x,
+                height: size.y,
+                depth: 1,
+            })
+            .build()],
+    );
+
+    transition_internal_image(
+        device,
+        command_buffer,
+        image,
+        (
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            AccessFlags::TRANSFER_READ,
+            PipelineStageFlags::TRANSFER,
+        ),
+        (
+            layout,
+            AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE,
+            PipelineStageFlags::COMPUTE_SHADER,
+        ),
+    );
+}
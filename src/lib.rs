@@ -1,32 +1,94 @@
+mod async_compute;
+#[cfg(feature = "bevy")]
+mod bevy_plugin;
+mod blit;
+mod capabilities;
+#[cfg(feature = "capture")]
+mod capture;
+mod color_space;
+mod debug;
+mod device;
+mod format;
+mod frame_clock;
 mod fsr;
+mod fsr1;
+mod internal_resource;
+mod memory;
+#[cfg(test)]
+mod mock;
+mod msaa;
+mod multiview;
+mod output;
+mod rcas;
+mod reset_detection;
+mod transparency_mask;
+mod upscaler;
+mod viewport;
 
+pub use crate::async_compute::Fsr2QueueFamilies;
+#[cfg(feature = "bevy")]
+pub use crate::bevy_plugin::{Fsr2CameraInfo, Fsr2Plugin, Fsr2Settings};
+pub use crate::capabilities::{Fsr2Capabilities, Fsr2DeviceCapabilities, Fsr2InsufficientLimit};
+#[cfg(feature = "capture")]
+pub use crate::capture::{CaptureManifest, CapturedFrame, CapturedTexture, Fsr2CaptureInput};
+pub use crate::color_space::Fsr2ColorSpace;
+pub use crate::device::Fsr2Device;
 pub use crate::fsr::{
-    Fsr2Error, Fsr2Exposure, Fsr2InitializationFlags, Fsr2QualityMode, Fsr2ReactiveMask,
-    Fsr2Texture, Fsr2WgpuError,
+    Fsr2AutoGenerateReactiveMaskFlags, Fsr2DebugResource, Fsr2Error, Fsr2Exposure,
+    Fsr2InitializationFlags, Fsr2QualityMode, Fsr2ReactiveMask, Fsr2Texture, Fsr2WgpuError,
 };
+pub use crate::fsr1::Fsr1Upscaler;
+pub use crate::memory::{
+    Fsr2Allocation, Fsr2AllocationCallbacks, Fsr2MemoryUsage, Fsr2ResourceMemoryUsage,
+};
+pub use crate::multiview::Fsr2MultiviewContext;
+pub use crate::rcas::RcasPass;
+pub use crate::reset_detection::Fsr2ResetThresholds;
+pub use crate::transparency_mask::{
+    TransparencyMaskGenerator, TransparencyMaskSource, TransparencyMaskSources,
+};
+pub use crate::upscaler::{BilinearUpscaler, NativeUpscaler, Upscaler, UpscalerInputs};
 pub use wgpu_hal::DeviceError;
 
+use crate::async_compute::{
+    record_ownership_transfer, transfer_ownership, transferred_textures, Transfer,
+};
+#[cfg(feature = "capture")]
+use crate::capture::FrameCapture;
+use crate::color_space::ColorSpaceConverter;
+use crate::debug::DebugVisualizer;
+use crate::format::FormatTable;
+use crate::frame_clock::{frame_delta_time, FrameClock};
 use crate::fsr::{
     ffxFsr2ContextCreate, ffxFsr2ContextDestroy, ffxFsr2ContextDispatch, ffxFsr2GetJitterOffset,
-    ffxFsr2GetJitterPhaseCount, ffx_check_result, FfxDimensions2D, FfxFloatCoords2D,
-    FfxFsr2Context, FfxFsr2ContextDescription, FfxFsr2DispatchDescription, FfxFsr2Interface,
-    FfxResource, FfxResourceStates, FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
+    ffxFsr2GetJitterPhaseCount, ffx_check_result, FfxCommandList, FfxDevice, FfxDimensions2D,
+    FfxFloatCoords2D, FfxFsr2Context, FfxFsr2ContextDescription, FfxFsr2DispatchDescription,
+    FfxFsr2Interface, FfxResource, FfxResourceStates,
+    FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
     FfxResourceStates_FFX_RESOURCE_STATE_GENERIC_READ,
     FfxResourceStates_FFX_RESOURCE_STATE_UNORDERED_ACCESS,
 };
 use crate::fsr::{
     ffxFsr2GetInterfaceVK, ffxFsr2GetScratchMemorySizeVK, ffxGetCommandListVK, ffxGetDeviceVK,
-    ffxGetTextureResourceVK,
+    ffxGetTextureResourceVK, FFX_FSR2_RESOURCE_IDENTIFIER_AUTO_EXPOSURE,
 };
+use crate::internal_resource::{copy_internal_image_to_buffer, copy_internal_image_to_image};
+use crate::msaa::MsaaResolver;
+use crate::output::ManagedOutput;
+use crate::reset_detection::ResetDetector;
+use crate::viewport::{is_depth_format, region_fits, CroppedInput, ViewportMapper};
 use arrayvec::ArrayVec;
-use ash::vk::{Format, Image, ImageView};
+use ash::vk::{self, Format, Image, ImageView, PFN_vkGetDeviceProcAddr};
 use glam::{Mat4, UVec2, Vec2, Vec3};
+use std::marker::PhantomData;
 use std::mem::MaybeUninit;
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 use std::ptr;
 use std::time::Duration;
 use wgpu::util::CommandEncoderExt;
-use wgpu::{Adapter, CommandEncoder, Device, Texture};
+use wgpu::{
+    Adapter, Buffer, BufferAddress, CommandEncoder, Device, Texture, TextureFormat, TextureView,
+};
 use wgpu_core::api::Vulkan;
 use wgpu_core::track::TextureSelector;
 use wgpu_hal::TextureUses;
@@ -37,24 +99,94 @@ use wgpu_hal::TextureUses;
 // TODO: GPU Debug spans
 // TODO: Validate inputs
 
+/// # Threading
+///
+/// `Fsr2Context` is [`Send`] and [`Sync`] (given `D` is), so it can be created on one thread and
+/// used to record commands on another. Every method that touches FSR's internal state takes
+/// `&mut self`, so sharing a context between threads that all record with it requires external
+/// synchronization, e.g. wrapping it in a [`std::sync::Mutex`].
 pub struct Fsr2Context<D: Deref<Target = Device>> {
-    context: FfxFsr2Context,
+    context: FfxContext,
     device: D,
-    upscaled_resolution: UVec2,
+    formats: FormatTable,
+    descriptor: Fsr2ContextDescriptor,
+    msaa_resolver: Option<MsaaResolver>,
+    managed_output: Option<ManagedOutput>,
+    viewport_mapper: Option<ViewportMapper>,
+    color_space_converter: Option<ColorSpaceConverter>,
+    debug_visualizer: Option<DebugVisualizer>,
+    #[cfg(feature = "capture")]
+    capture: Option<FrameCapture>,
+    frame_clock: FrameClock,
+    reset_detector: Option<ResetDetector>,
+    /// `None` until the first frame has been dispatched.
+    last_input_resolution: Option<UVec2>,
     _scratch_memory: Vec<u8>,
 }
 
+/// An FSR context, which is opaque data to bindgen, but actually holds raw pointers into the
+/// scratch memory and to Vulkan objects, so opts out of the auto traits to implement them below.
+struct FfxContext {
+    context: FfxFsr2Context,
+    _not_send_sync: PhantomData<*mut u8>,
+}
+
+// SAFETY: The Vulkan backend keeps all of its state in the context and its scratch memory (which
+// is heap allocated, and so doesn't move with the context), and has no thread local state.
+// Vulkan handles are valid to use from any thread as long as access is externally synchronized,
+// which &mut guarantees for every call into FSR that changes the context.
+unsafe impl Send for FfxContext {}
+
+// SAFETY: The only FSR function called through a shared reference is ffxGetVkImage, in
+// Fsr2Context::memory_usage, which only reads image handles out of the backend's resource table.
+// That table is only written while creating the context and when dispatching, which both take
+// &mut. Querying the memory requirements of those images doesn't need external synchronization.
+unsafe impl Sync for FfxContext {}
+
+impl Deref for FfxContext {
+    type Target = FfxFsr2Context;
+
+    fn deref(&self) -> &FfxFsr2Context {
+        &self.context
+    }
+}
+
+impl DerefMut for FfxContext {
+    fn deref_mut(&mut self) -> &mut FfxFsr2Context {
+        &mut self.context
+    }
+}
+
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[allow(dead_code)]
+    fn assert_fsr2_context_send_sync() {
+        assert_send_sync::<Fsr2Context<&'static Device>>();
+        assert_send_sync::<Fsr2Context<std::sync::Arc<Device>>>();
+    }
+};
+
 impl<D: Deref<Target = Device>> Fsr2Context<D> {
     pub fn new(
         device: D,
-        max_input_resolution: UVec2,
-        upscaled_resolution: UVec2,
-        initialization_flags: Fsr2InitializationFlags,
+        adapter: &Adapter,
+        descriptor: Fsr2ContextDescriptor,
+    ) -> Result<Self, Fsr2Error> {
+        unsafe { Self::create(device, FormatTable::new(adapter), descriptor, None) }
+    }
+
+    /// `get_device_proc_addr` replaces the instance's as the way FSR loads Vulkan functions.
+    pub(crate) unsafe fn create(
+        device: D,
+        formats: FormatTable,
+        descriptor: Fsr2ContextDescriptor,
+        get_device_proc_addr: Option<PFN_vkGetDeviceProcAddr>,
     ) -> Result<Self, Fsr2Error> {
         unsafe {
             // Get underlying Vulkan objects from wgpu
-            let (vk_device, physical_device, get_device_proc_addr) =
-                device.as_hal::<Vulkan, _, _>(|device| {
+            let (vk_device, physical_device, instance_get_device_proc_addr) = device
+                .as_hal::<Vulkan, _, _>(|device| {
                     let device = device.unwrap();
                     let raw_device = device.raw_device().handle();
                     let physical_device = device.raw_physical_device();
@@ -67,6 +199,8 @@ impl<D: Deref<Target = Device>> Fsr2Context<D> {
 
                     (raw_device, physical_device, get_device_proc_addr)
                 });
+            let get_device_proc_addr =
+                get_device_proc_addr.unwrap_or(instance_get_device_proc_addr);
 
             // Allocate scratch memory for FSR
             let scratch_memory_size = ffxFsr2GetScratchMemorySizeVK(physical_device);
@@ -85,42 +219,92 @@ impl<D: Deref<Target = Device>> Fsr2Context<D> {
 
             // Create an FSR context
             let mut context = MaybeUninit::<FfxFsr2Context>::uninit();
-            let context_description = FfxFsr2ContextDescription {
-                flags: initialization_flags.bits() as u32,
-                maxRenderSize: uvec2_to_dim2d(max_input_resolution),
-                displaySize: uvec2_to_dim2d(upscaled_resolution),
-                callbacks: interface,
-                device: ffxGetDeviceVK(vk_device),
-            };
+            let context_description = context_description(
+                descriptor.initialization_flags,
+                descriptor.max_input_resolution,
+                descriptor.upscaled_resolution,
+                interface,
+                ffxGetDeviceVK(vk_device),
+            );
             ffx_check_result(ffxFsr2ContextCreate(
                 context.as_mut_ptr(),
                 &context_description as *const _,
             ))?;
             let context = context.assume_init();
 
+            let msaa_resolver = descriptor.msaa_resolve.then(|| {
+                MsaaResolver::new(
+                    &device,
+                    descriptor.max_input_resolution,
+                    descriptor
+                        .initialization_flags
+                        .contains(Fsr2InitializationFlags::INVERTED_DEPTH),
+                )
+            });
+            let encoded_output = descriptor
+                .color_space
+                .map_or(false, Fsr2ColorSpace::is_encoded);
+            let managed_output = descriptor.managed_output_format.map(|format| {
+                ManagedOutput::new(
+                    &device,
+                    descriptor.upscaled_resolution,
+                    format,
+                    encoded_output,
+                )
+            });
+            let color_space_converter = descriptor
+                .color_space
+                .filter(|color_space| color_space.is_encoded())
+                .map(|color_space| {
+                    ColorSpaceConverter::new(
+                        &device,
+                        color_space,
+                        descriptor.max_input_resolution,
+                        descriptor.upscaled_resolution,
+                    )
+                });
+            let reset_detector = descriptor.reset_detection.map(|thresholds| {
+                ResetDetector::new(
+                    thresholds,
+                    descriptor
+                        .initialization_flags
+                        .contains(Fsr2InitializationFlags::DYNAMIC_RESOLUTION),
+                )
+            });
+
             Ok(Self {
-                context,
+                context: FfxContext {
+                    context,
+                    _not_send_sync: PhantomData,
+                },
                 device,
-                upscaled_resolution,
+                formats,
+                msaa_resolver,
+                managed_output,
+                viewport_mapper: None,
+                color_space_converter,
+                debug_visualizer: None,
+                #[cfg(feature = "capture")]
+                capture: None,
+                frame_clock: FrameClock::new(),
+                reset_detector,
+                last_input_resolution: None,
+                descriptor,
                 _scratch_memory: scratch_memory,
             })
         }
     }
 
     pub fn suggested_input_resolution(&self, quality_mode: Fsr2QualityMode) -> UVec2 {
-        let scale_factor = match quality_mode {
-            Fsr2QualityMode::Native => 1.0,
-            Fsr2QualityMode::Quality => 1.5,
-            Fsr2QualityMode::Balanced => 1.7,
-            Fsr2QualityMode::Performance => 2.0,
-            Fsr2QualityMode::UltraPerformance => 3.0,
-        };
+        quality_mode.input_resolution(self.descriptor.upscaled_resolution)
+    }
 
-        (self.upscaled_resolution.as_vec2() / scale_factor).as_uvec2()
+    pub fn descriptor(&self) -> &Fsr2ContextDescriptor {
+        &self.descriptor
     }
 
     pub fn upscaled_resolution(&self) -> UVec2 {
-        self.upscaled_resolution
+        self.descriptor.upscaled_resolution
     }
 
     pub fn jitter_camera_projection_matrix(
@@ -150,7 +334,7 @@ impl<D: Deref<Target = Device>> Fsr2Context<D> {
         unsafe {
             let phase_count = ffxFsr2GetJitterPhaseCount(
                 input_resolution.x.try_into().unwrap(),
-                self.upscaled_resolution.x.try_into().unwrap(),
+                self.descriptor.upscaled_resolution.x.try_into().unwrap(),
             );
 
             let mut jitter_offset = Vec2::ZERO;
@@ -165,168 +349,789 @@ impl<D: Deref<Target = Device>> Fsr2Context<D> {
     }
 
     pub fn suggested_mip_bias(&self, input_resolution: UVec2) -> f32 {
-        (input_resolution.x as f32 / self.upscaled_resolution.x as f32).log2() - 1.0
+        (input_resolution.x as f32 / self.descriptor.upscaled_resolution.x as f32).log2() - 1.0
     }
 
-    pub fn render(&mut self, parameters: Fsr2RenderParameters) -> Result<(), Fsr2WgpuError> {
-        let mut texture_transitions = ArrayVec::<_, 7>::new();
+    /// Returns the managed output texture written to this frame, if
+    /// [`Fsr2RenderParameters::output`] was `None`.
+    pub fn render(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        parameters: Fsr2RenderParameters,
+    ) -> Result<Option<Fsr2Texture<'_>>, Fsr2WgpuError> {
+        self.render_layer(command_encoder, parameters, 0)
+    }
 
-        let (exposure, pre_exposure) = match parameters.exposure {
-            Fsr2Exposure::AutoExposure => (None, 1.0),
-            Fsr2Exposure::ManualExposure {
-                pre_exposure,
-                exposure,
-            } => (Some(exposure), pre_exposure),
+    /// With async compute, see [`Fsr2ContextDescriptor::queue_families`], record FSR into
+    /// `command_buffer` for the compute queue, instead of [`Fsr2Context::render`]. The textures of
+    /// `parameters` are acquired from the graphics queue family first, and released back to it
+    /// afterwards, including when an error is returned.
+    ///
+    /// Only FSR itself runs on the compute queue, so MSAA inputs, input and output offsets, color
+    /// space conversion and capture aren't available.
+    ///
+    /// Returns the managed output texture written to this frame, if
+    /// [`Fsr2RenderParameters::output`] was `None`.
+    ///
+    /// # Safety
+    /// `command_buffer` must be in the recording state, and allocated from a command pool of the
+    /// compute queue family on this context's device.
+    pub unsafe fn render_on_compute(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        parameters: Fsr2RenderParameters,
+    ) -> Result<Option<Fsr2Texture<'_>>, Fsr2WgpuError> {
+        self.render_layer_on_compute(command_buffer, parameters, 0)
+    }
+
+    /// With async compute, see [`Fsr2ContextDescriptor::queue_families`], record the release of
+    /// the textures of `parameters` to the compute queue, once they've been rendered on the
+    /// graphics queue. Does nothing otherwise.
+    pub fn release_to_compute(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        parameters: &Fsr2RenderParameters,
+    ) {
+        self.transfer_textures(command_encoder, parameters, 0, Transfer::Release);
+    }
+
+    /// With async compute, see [`Fsr2ContextDescriptor::queue_families`], record the acquisition
+    /// of the textures of `parameters` back from the compute queue, before the output is read on
+    /// the graphics queue. Does nothing otherwise.
+    ///
+    /// `parameters` are the ones the frame was rendered with.
+    pub fn acquire_from_compute(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        parameters: &Fsr2RenderParameters,
+    ) {
+        self.transfer_textures(command_encoder, parameters, 0, Transfer::Acquire);
+    }
+
+    fn is_async_compute(&self) -> bool {
+        self.descriptor
+            .queue_families
+            .map_or(false, Fsr2QueueFamilies::is_async)
+    }
+
+    /// The queue families and the textures of `parameters` that move between them with async
+    /// compute. The managed output is the current one once it's been `advanced`, which only
+    /// happens when the frame was rendered successfully.
+    fn async_compute_textures<'a>(
+        &'a self,
+        parameters: &Fsr2RenderParameters<'a>,
+        advanced: bool,
+    ) -> Option<(Fsr2QueueFamilies, ArrayVec<Fsr2Texture<'a>, 7>)> {
+        let queue_families = self
+            .descriptor
+            .queue_families
+            .filter(|queue_families| queue_families.is_async())?;
+        let output = match (parameters.output, &self.managed_output) {
+            (Some(output), _) => output,
+            (None, Some(managed_output)) if advanced => managed_output.current(),
+            (None, Some(managed_output)) => managed_output.next(),
+            (None, None) => return None,
         };
 
-        unsafe {
-            let command_buffer = parameters
-                .command_encoder
-                .as_hal_mut::<Vulkan, _, _>(|cmd_encoder| cmd_encoder.unwrap().raw_handle());
+        Some((queue_families, transferred_textures(parameters, output)))
+    }
 
-            let reactive = match parameters.reactive_mask {
-                Fsr2ReactiveMask::NoMask => self.input_texture_to_ffx_resource(
-                    None,
-                    TextureUses::RESOURCE,
-                    FfxResourceStates_FFX_RESOURCE_STATE_GENERIC_READ,
-                    &mut texture_transitions,
-                    parameters.adapter,
-                ),
-                Fsr2ReactiveMask::ManualMask(mask) => self.input_texture_to_ffx_resource(
-                    Some(mask),
-                    TextureUses::RESOURCE,
-                    FfxResourceStates_FFX_RESOURCE_STATE_GENERIC_READ,
+    /// Record the graphics queue's side of `transfer`: releasing the textures of `parameters`
+    /// before the frame is rendered, or acquiring them once it has been.
+    pub(crate) fn transfer_textures(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        parameters: &Fsr2RenderParameters,
+        layer: u32,
+        transfer: Transfer,
+    ) {
+        let advanced = matches!(transfer, Transfer::Acquire);
+        let Some((queue_families, textures)) = self.async_compute_textures(parameters, advanced)
+        else {
+            return;
+        };
+        let families = match transfer {
+            Transfer::Release => (queue_families.graphics, queue_families.compute),
+            Transfer::Acquire => (queue_families.compute, queue_families.graphics),
+        };
+
+        transfer_ownership(
+            &self.device,
+            command_encoder,
+            &textures,
+            layer,
+            families,
+            transfer,
+        );
+    }
+
+    /// Record the compute queue's side of `transfer` into `command_buffer`: acquiring the
+    /// textures of `parameters` before FSR's dispatch, or releasing them after it.
+    unsafe fn transfer_textures_on_compute(
+        &self,
+        raw_device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        parameters: &Fsr2RenderParameters,
+        layer: u32,
+        advanced: bool,
+        transfer: Transfer,
+    ) {
+        let Some((queue_families, textures)) = self.async_compute_textures(parameters, advanced)
+        else {
+            return;
+        };
+        let families = match transfer {
+            Transfer::Acquire => (queue_families.graphics, queue_families.compute),
+            Transfer::Release => (queue_families.compute, queue_families.graphics),
+        };
+
+        record_ownership_transfer(
+            raw_device,
+            command_buffer,
+            &textures,
+            layer,
+            families,
+            transfer,
+        );
+    }
+
+    /// Like [`Fsr2Context::render_on_compute`], but with the given views being of `layer` of any
+    /// array textures.
+    pub(crate) unsafe fn render_layer_on_compute(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        parameters: Fsr2RenderParameters,
+        layer: u32,
+    ) -> Result<Option<Fsr2Texture<'_>>, Fsr2WgpuError> {
+        if let Some(color_space) = self.descriptor.color_space {
+            color_space.validate(
+                self.descriptor.initialization_flags,
+                parameters.color.texture.format(),
+            )?;
+        }
+        #[cfg(feature = "capture")]
+        let capturing = self.capture.is_some();
+        #[cfg(not(feature = "capture"))]
+        let capturing = false;
+        if parameters.color.texture.sample_count() > 1
+            || parameters.depth.texture.sample_count() > 1
+            || parameters.input_offset != UVec2::ZERO
+            || (parameters.output.is_some() && parameters.output_offset != UVec2::ZERO)
+            || self.color_space_converter.is_some()
+            || capturing
+        {
+            return Err(Fsr2WgpuError::AsyncComputeUnsupported);
+        }
+        if parameters.output.is_none() && self.managed_output.is_none() {
+            return Err(Fsr2WgpuError::MissingOutput);
+        }
+
+        let (snapshot, history) = self.begin_frame(&parameters);
+
+        let raw_device = self
+            .device
+            .as_hal::<Vulkan, _, _>(|device| device.unwrap().raw_device().clone());
+        self.transfer_textures_on_compute(
+            &raw_device,
+            command_buffer,
+            &parameters,
+            layer,
+            false,
+            Transfer::Acquire,
+        );
+
+        let output = match parameters.output {
+            Some(output) => output,
+            None => self.managed_output.as_ref().unwrap().next(),
+        };
+        let exposure = match parameters.exposure {
+            Fsr2Exposure::AutoExposure => None,
+            Fsr2Exposure::ManualExposure { exposure, .. } => Some(exposure),
+        };
+        let inputs = DispatchInputs {
+            color: parameters.color,
+            depth: parameters.depth,
+            motion_vectors: parameters.motion_vectors,
+            exposure,
+            reactive_mask: reactive_mask_texture(parameters.reactive_mask),
+            transparency_and_composition_mask: parameters.transparency_and_composition_mask,
+            output,
+        };
+        // The graphics queue already transitioned every texture to these uses when releasing it
+        let mut texture_transitions = ArrayVec::<_, 7>::new();
+        let dispatched = inputs
+            .into_resources(|texture, new_use, resource_state| {
+                input_texture_to_ffx_resource(
+                    &mut self.context,
+                    texture,
+                    new_use,
+                    resource_state,
+                    layer,
                     &mut texture_transitions,
-                    parameters.adapter,
+                    &self.formats,
+                )
+            })
+            .and_then(|resources| {
+                let dispatch_description =
+                    dispatch_description(ffxGetCommandListVK(command_buffer), resources, &snapshot);
+                ffx_check_result(ffxFsr2ContextDispatch(
+                    &mut *self.context as *mut _,
+                    &dispatch_description as *const _,
+                ))
+            });
+        if let Err(error) = dispatched {
+            self.transfer_textures_on_compute(
+                &raw_device,
+                command_buffer,
+                &parameters,
+                layer,
+                false,
+                Transfer::Release,
+            );
+            self.abandon_frame(history);
+            return Err(error);
+        }
+
+        let use_managed_output = parameters.output.is_none();
+        if use_managed_output {
+            self.managed_output.as_mut().unwrap().advance();
+        }
+        self.transfer_textures_on_compute(
+            &raw_device,
+            command_buffer,
+            &parameters,
+            layer,
+            true,
+            Transfer::Release,
+        );
+        self.last_input_resolution = Some(parameters.input_resolution);
+
+        Ok(use_managed_output.then(|| self.managed_output.as_ref().unwrap().current()))
+    }
+
+    /// Tick the frame clock and reset detector for a frame about to be dispatched, returning the
+    /// parameters FSR is given, and their state from before the frame for
+    /// [`Fsr2Context::abandon_frame`].
+    fn begin_frame(
+        &mut self,
+        parameters: &Fsr2RenderParameters,
+    ) -> (Fsr2RenderParametersSnapshot, FrameHistory) {
+        let history = FrameHistory {
+            frame_clock: self.frame_clock.clone(),
+            reset_detector: self.reset_detector.clone(),
+        };
+
+        // Keep the clock ticking even when the caller provides the frame delta time, so that
+        // switching to the clock later doesn't report the whole time since it was last used
+        let measured_frame_delta_time = self.frame_clock.tick();
+        let mut snapshot = Fsr2RenderParametersSnapshot {
+            frame_delta_time: frame_delta_time(
+                parameters.frame_delta_time,
+                measured_frame_delta_time,
+            ),
+            ..parameters.snapshot()
+        };
+        if let Some(reset_detector) = &mut self.reset_detector {
+            let elapsed = parameters
+                .frame_delta_time
+                .unwrap_or(measured_frame_delta_time);
+            snapshot.reset |= reset_detector.should_reset(&snapshot, elapsed);
+        }
+
+        (snapshot, history)
+    }
+
+    /// Restore the frame clock and reset detector after a frame failed to be dispatched, so that
+    /// it doesn't count towards the next one's delta time or reset.
+    fn abandon_frame(&mut self, history: FrameHistory) {
+        self.frame_clock = history.frame_clock;
+        self.reset_detector = history.reset_detector;
+    }
+
+    /// Like [`Fsr2Context::render`], but with the given views being of `layer` of any array
+    /// textures.
+    pub(crate) fn render_layer(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        parameters: Fsr2RenderParameters,
+        layer: u32,
+    ) -> Result<Option<Fsr2Texture<'_>>, Fsr2WgpuError> {
+        // Validate everything before the frame clock and reset detector see the frame, so that a
+        // rejected frame doesn't count towards the next one's delta time or reset
+        if let Some(color_space) = self.descriptor.color_space {
+            color_space.validate(
+                self.descriptor.initialization_flags,
+                parameters.color.texture.format(),
+            )?;
+        }
+        if (parameters.color.texture.sample_count() > 1
+            || parameters.depth.texture.sample_count() > 1)
+            && self.msaa_resolver.is_none()
+        {
+            return Err(Fsr2WgpuError::MultisampledInput);
+        }
+        if parameters.output.is_none() && self.managed_output.is_none() {
+            return Err(Fsr2WgpuError::MissingOutput);
+        }
+        if self.is_async_compute() {
+            return Err(Fsr2WgpuError::AsyncComputeEncoder);
+        }
+
+        let input_offset = parameters.input_offset;
+        if input_offset != UVec2::ZERO {
+            if parameters.color.texture.sample_count() > 1
+                || parameters.depth.texture.sample_count() > 1
+            {
+                return Err(Fsr2WgpuError::MultisampledInputAtOffset);
+            }
+
+            let mut inputs = vec![
+                (Some(CroppedInput::Color), parameters.color),
+                (
+                    (!is_depth_format(parameters.depth.texture.format()))
+                        .then_some(CroppedInput::Depth),
+                    parameters.depth,
                 ),
-                #[allow(unused_variables)]
-                Fsr2ReactiveMask::AutoMask {
-                    color_opaque_only,
-                    color_opaque_and_transparent,
-                    scale,
-                    threshold,
-                    binary_value,
-                    flags,
-                } => {
-                    todo!()
+                (Some(CroppedInput::MotionVectors), parameters.motion_vectors),
+            ];
+            if let Fsr2ReactiveMask::ManualMask(mask) = parameters.reactive_mask {
+                inputs.push((Some(CroppedInput::ReactiveMask), mask));
+            }
+            if let Some(mask) = parameters.transparency_and_composition_mask {
+                inputs.push((Some(CroppedInput::TransparencyAndCompositionMask), mask));
+            }
+
+            let viewport_mapper = self.viewport_mapper.get_or_insert_with(|| {
+                ViewportMapper::new(
+                    &self.device,
+                    self.descriptor.max_input_resolution,
+                    self.descriptor.upscaled_resolution,
+                )
+            });
+            for (input, texture) in inputs {
+                if !region_fits(texture.texture, input_offset, parameters.input_resolution) {
+                    return Err(Fsr2WgpuError::RegionOutOfBounds);
                 }
-            };
+                if let Some(input) = input {
+                    viewport_mapper.prepare_input(&self.device, input, texture.texture.format());
+                }
+            }
+        }
+        let placed_output = parameters
+            .output
+            .filter(|_| parameters.output_offset != UVec2::ZERO);
+        if let Some(output) = placed_output {
+            if !region_fits(
+                output.texture,
+                parameters.output_offset,
+                self.descriptor.upscaled_resolution,
+            ) {
+                return Err(Fsr2WgpuError::RegionOutOfBounds);
+            }
+            self.viewport_mapper
+                .get_or_insert_with(|| {
+                    ViewportMapper::new(
+                        &self.device,
+                        self.descriptor.max_input_resolution,
+                        self.descriptor.upscaled_resolution,
+                    )
+                })
+                .prepare_output(
+                    &self.device,
+                    output.texture.format(),
+                    self.color_space_converter.is_some(),
+                );
+        }
 
-            let dispatch_description = FfxFsr2DispatchDescription {
-                commandList: ffxGetCommandListVK(command_buffer),
-                color: self.input_texture_to_ffx_resource(
-                    Some(parameters.color),
-                    TextureUses::RESOURCE,
-                    FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
-                    &mut texture_transitions,
-                    parameters.adapter,
-                ),
-                depth: self.input_texture_to_ffx_resource(
-                    Some(parameters.depth),
-                    TextureUses::RESOURCE, // TODO: Needs to be SHADER_READ_ONLY, not depth stencil
-                    FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
-                    &mut texture_transitions,
-                    parameters.adapter,
-                ),
-                motionVectors: self.input_texture_to_ffx_resource(
-                    Some(parameters.motion_vectors),
-                    TextureUses::RESOURCE,
-                    FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
-                    &mut texture_transitions,
-                    parameters.adapter,
-                ),
-                exposure: self.input_texture_to_ffx_resource(
-                    exposure,
-                    TextureUses::RESOURCE,
-                    FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
-                    &mut texture_transitions,
-                    parameters.adapter,
-                ),
-                reactive,
-                transparencyAndComposition: self.input_texture_to_ffx_resource(
+        let (snapshot, history) = self.begin_frame(&parameters);
+        if let Err(error) = self.upscale(command_encoder, parameters, layer, snapshot) {
+            self.abandon_frame(history);
+            return Err(error);
+        }
+
+        let use_managed_output = parameters.output.is_none();
+        if use_managed_output {
+            self.managed_output.as_mut().unwrap().advance();
+        }
+        self.last_input_resolution = Some(parameters.input_resolution);
+
+        Ok(use_managed_output.then(|| self.managed_output.as_ref().unwrap().current()))
+    }
+
+    /// Record the passes preparing the inputs of `parameters`, FSR's dispatch, and the passes
+    /// writing its output.
+    fn upscale(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        parameters: Fsr2RenderParameters,
+        layer: u32,
+        snapshot: Fsr2RenderParametersSnapshot,
+    ) -> Result<(), Fsr2WgpuError> {
+        let mut texture_transitions = ArrayVec::<_, 7>::new();
+        let input_offset = parameters.input_offset;
+        let placed_output = parameters
+            .output
+            .filter(|_| parameters.output_offset != UVec2::ZERO);
+        let exposure = match parameters.exposure {
+            Fsr2Exposure::AutoExposure => None,
+            Fsr2Exposure::ManualExposure { exposure, .. } => Some(exposure),
+        };
+
+        let color = if parameters.color.texture.sample_count() > 1 {
+            self.msaa_resolver
+                .as_ref()
+                .ok_or(Fsr2WgpuError::MultisampledInput)?
+                .resolve_color(
+                    &self.device,
+                    command_encoder,
+                    &parameters.color,
+                    parameters.input_resolution,
+                )
+        } else {
+            parameters.color
+        };
+        let depth = if parameters.depth.texture.sample_count() > 1 {
+            self.msaa_resolver
+                .as_ref()
+                .ok_or(Fsr2WgpuError::MultisampledInput)?
+                .resolve_depth(
+                    &self.device,
+                    command_encoder,
+                    &parameters.depth,
+                    parameters.input_resolution,
+                )
+        } else {
+            parameters.depth
+        };
+
+        // FSR only reads from the top left corner of its inputs, so move the region there
+        let (color, depth, motion_vectors, reactive_mask, transparency_and_composition_mask) =
+            match self.viewport_mapper.as_ref() {
+                Some(viewport_mapper) if input_offset != UVec2::ZERO => {
+                    let size = parameters.input_resolution;
+                    let depth = if is_depth_format(depth.texture.format()) {
+                        viewport_mapper.crop_depth(
+                            &self.device,
+                            command_encoder,
+                            &depth,
+                            input_offset,
+                            size,
+                        )
+                    } else {
+                        viewport_mapper.crop_input(
+                            command_encoder,
+                            CroppedInput::Depth,
+                            &depth,
+                            input_offset,
+                            size,
+                            layer,
+                        )
+                    };
+                    let mut crop = |input, texture: &Fsr2Texture| {
+                        viewport_mapper.crop_input(
+                            command_encoder,
+                            input,
+                            texture,
+                            input_offset,
+                            size,
+                            layer,
+                        )
+                    };
+                    let color = crop(CroppedInput::Color, &color);
+                    let motion_vectors =
+                        crop(CroppedInput::MotionVectors, &parameters.motion_vectors);
+                    let reactive_mask = match parameters.reactive_mask {
+                        Fsr2ReactiveMask::ManualMask(mask) => {
+                            Fsr2ReactiveMask::ManualMask(crop(CroppedInput::ReactiveMask, &mask))
+                        }
+                        reactive_mask => reactive_mask,
+                    };
+                    let transparency_and_composition_mask = parameters
+                        .transparency_and_composition_mask
+                        .map(|mask| crop(CroppedInput::TransparencyAndCompositionMask, &mask));
+                    (
+                        color,
+                        depth,
+                        motion_vectors,
+                        reactive_mask,
+                        transparency_and_composition_mask,
+                    )
+                }
+                _ => (
+                    color,
+                    depth,
+                    parameters.motion_vectors,
+                    parameters.reactive_mask,
                     parameters.transparency_and_composition_mask,
-                    TextureUses::RESOURCE,
-                    FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
-                    &mut texture_transitions,
-                    parameters.adapter,
                 ),
-                output: self.input_texture_to_ffx_resource(
-                    Some(parameters.output),
-                    TextureUses::RESOURCE, // TODO: Needs to be GENERAL, not SHADER_READ_ONLY
-                    FfxResourceStates_FFX_RESOURCE_STATE_UNORDERED_ACCESS,
-                    &mut texture_transitions,
-                    parameters.adapter,
-                ),
-                jitterOffset: vec2_to_float_coords2d(parameters.jitter_offset),
-                motionVectorScale: vec2_to_float_coords2d(
-                    parameters.motion_vector_scale.unwrap_or(Vec2::ONE),
-                ),
-                renderSize: uvec2_to_dim2d(parameters.input_resolution),
-                enableSharpening: parameters.sharpness > 0.0,
-                sharpness: parameters.sharpness.clamp(0.0, 1.0),
-                frameTimeDelta: parameters.frame_delta_time.as_millis() as f32,
-                preExposure: pre_exposure,
-                reset: parameters.reset,
-                cameraNear: parameters.camera_near,
-                cameraFar: parameters.camera_far.unwrap_or(0.0),
-                cameraFovAngleVertical: parameters.camera_fov_angle_vertical,
             };
 
-            parameters
-                .command_encoder
-                .transition_textures(&texture_transitions);
+        let color = match &self.color_space_converter {
+            Some(color_space_converter) => color_space_converter.decode(
+                &self.device,
+                command_encoder,
+                &color,
+                parameters.input_resolution,
+            ),
+            None => color,
+        };
+
+        let output = match parameters.output {
+            Some(_) if placed_output.is_some() => self.viewport_mapper.as_ref().unwrap().output(),
+            Some(output) => output,
+            // Only advanced once the frame has been dispatched, so that a failed frame doesn't
+            // leave the previous output to be overwritten
+            None => self
+                .managed_output
+                .as_ref()
+                .ok_or(Fsr2WgpuError::MissingOutput)?
+                .next(),
+        };
+        // FSR writes linear color, to be encoded into the output afterwards
+        let (output, encoded_output) = match &self.color_space_converter {
+            Some(color_space_converter) => (color_space_converter.linear_output(), Some(output)),
+            None => (output, None),
+        };
+
+        #[cfg(feature = "capture")]
+        if let Some(capture) = &mut self.capture {
+            // Earlier frames have been submitted by now, so can be read back once the GPU is done
+            capture.flush(&self.device, false)?;
+
+            let mut inputs = vec![
+                (Fsr2CaptureInput::Color, color),
+                (Fsr2CaptureInput::Depth, depth),
+                (Fsr2CaptureInput::MotionVectors, motion_vectors),
+            ];
+            if let Some(exposure) = exposure {
+                inputs.push((Fsr2CaptureInput::Exposure, exposure));
+            }
+            if let Fsr2ReactiveMask::ManualMask(mask) = reactive_mask {
+                inputs.push((Fsr2CaptureInput::ReactiveMask, mask));
+            }
+            if let Some(mask) = transparency_and_composition_mask {
+                inputs.push((Fsr2CaptureInput::TransparencyAndCompositionMask, mask));
+            }
+            capture.record(&self.device, command_encoder, snapshot, &inputs, layer)?;
+
+            if capture.is_finished() {
+                self.capture = None;
+            }
+        }
+
+        unsafe {
+            let command_buffer = command_encoder
+                .as_hal_mut::<Vulkan, _, _>(|cmd_encoder| cmd_encoder.unwrap().raw_handle());
+
+            let inputs = DispatchInputs {
+                color,
+                depth,
+                motion_vectors,
+                exposure,
+                reactive_mask: reactive_mask_texture(reactive_mask),
+                transparency_and_composition_mask,
+                output,
+            };
+            let resources = inputs.into_resources(|texture, new_use, resource_state| {
+                input_texture_to_ffx_resource(
+                    &mut self.context,
+                    texture,
+                    new_use,
+                    resource_state,
+                    layer,
+                    &mut texture_transitions,
+                    &self.formats,
+                )
+            })?;
+            let dispatch_description =
+                dispatch_description(ffxGetCommandListVK(command_buffer), resources, &snapshot);
+
+            command_encoder.transition_textures(&texture_transitions);
             ffx_check_result(ffxFsr2ContextDispatch(
-                &mut self.context as *mut _,
+                &mut *self.context as *mut _,
                 &dispatch_description as *const _,
             ))?;
         }
 
+        if let Some(encoded_output) = encoded_output {
+            self.color_space_converter.as_mut().unwrap().encode(
+                &self.device,
+                command_encoder,
+                &encoded_output,
+            );
+        }
+        if let Some(output) = placed_output {
+            self.viewport_mapper.as_ref().unwrap().place_output(
+                command_encoder,
+                &output,
+                parameters.output_offset,
+                layer,
+            );
+        }
+
         Ok(())
     }
 
-    unsafe fn input_texture_to_ffx_resource<'a>(
+    /// Capture the inputs of the next `frame_count` calls to [`Fsr2Context::render`] into
+    /// `directory`.
+    ///
+    /// Each frame is written to disk by the first following call to [`Fsr2Context::render`] after
+    /// the GPU is finished with it, without waiting on the GPU, or by
+    /// [`Fsr2Context::finish_capture`]. See [`CaptureManifest`] for
+    /// the layout of the capture directory.
+    #[cfg(feature = "capture")]
+    pub fn start_capture(
         &mut self,
-        texture: Option<Fsr2Texture<'a>>,
-        new_use: TextureUses,
-        resource_state: FfxResourceStates,
-        texture_uses: &mut ArrayVec<(&'a Texture, TextureUses, TextureSelector), 7>,
-        adapter: &Adapter,
-    ) -> FfxResource {
-        match texture {
-            Some(Fsr2Texture { texture, view }) => {
-                texture_uses.push((
-                    texture,
-                    new_use,
-                    TextureSelector {
-                        mips: 0..1,
-                        layers: 0..1,
-                    },
-                ));
-
-                ffxGetTextureResourceVK(
-                    &mut self.context as *mut _,
-                    texture.as_hal::<Vulkan, _, _>(|texture| texture.unwrap().raw_handle()),
-                    view.as_hal::<Vulkan, _, _>(|view| view.unwrap().raw_handle()),
-                    texture.width(),
-                    texture.height(),
-                    adapter
-                        .texture_format_as_hal::<Vulkan>(texture.format())
-                        .unwrap(),
-                    ptr::null_mut(),
-                    resource_state,
-                )
-            }
-            None => ffxGetTextureResourceVK(
-                &mut self.context as *mut _,
-                Image::null(),
-                ImageView::null(),
-                1,
-                1,
-                Format::UNDEFINED,
-                ptr::null_mut(),
-                resource_state,
-            ),
+        directory: impl AsRef<std::path::Path>,
+        frame_count: u32,
+    ) -> Result<(), Fsr2WgpuError> {
+        self.capture = Some(FrameCapture::new(
+            directory.as_ref(),
+            frame_count,
+            &self.descriptor,
+        )?);
+        Ok(())
+    }
+
+    /// Wait for any captured frames still being read back, write them to disk, and stop capturing.
+    ///
+    /// The command encoder passed to the last [`Fsr2Context::render`] must have been submitted.
+    #[cfg(feature = "capture")]
+    pub fn finish_capture(&mut self) -> Result<(), Fsr2WgpuError> {
+        if let Some(mut capture) = self.capture.take() {
+            capture.flush(&self.device, true)?;
         }
+        Ok(())
+    }
+
+    /// Copy the exposure value computed by [`Fsr2InitializationFlags::AUTO_EXPOSURE`] into a buffer.
+    ///
+    /// Writes 8 bytes at `offset`: an f32 exposure value, followed by an f32 average luminance value.
+    /// The buffer must have been created with [`wgpu::BufferUsages::COPY_DST`].
+    /// The copy reflects the most recent call to [`Fsr2Context::render`] recorded before it, so
+    /// fails with [`Fsr2WgpuError::NotYetDispatched`] before the first.
+    pub fn copy_auto_exposure_to_buffer(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        buffer: &Buffer,
+        offset: BufferAddress,
+    ) -> Result<(), Fsr2WgpuError> {
+        if !self
+            .descriptor
+            .initialization_flags
+            .contains(Fsr2InitializationFlags::AUTO_EXPOSURE)
+        {
+            return Err(Fsr2WgpuError::AutoExposureDisabled);
+        }
+        if self.last_input_resolution.is_none() {
+            return Err(Fsr2WgpuError::NotYetDispatched);
+        }
+
+        unsafe {
+            let raw_device = self
+                .device
+                .as_hal::<Vulkan, _, _>(|device| device.unwrap().raw_device().clone());
+            let command_buffer = command_encoder
+                .as_hal_mut::<Vulkan, _, _>(|cmd_encoder| cmd_encoder.unwrap().raw_handle());
+
+            copy_internal_image_to_buffer(
+                &mut self.context,
+                &raw_device,
+                command_buffer,
+                FFX_FSR2_RESOURCE_IDENTIFIER_AUTO_EXPOSURE,
+                UVec2::ONE,
+                buffer.as_hal::<Vulkan, _, _>(|buffer| buffer.unwrap().raw_handle()),
+                offset,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Copy one of FSR's internal resources into a texture owned by the context, for debugging.
+    ///
+    /// Render resolution resources are allocated at the max input resolution, but are only valid
+    /// within the input resolution of the last [`Fsr2Context::render`]. Resources FSR alternates
+    /// between every frame, like [`Fsr2DebugResource::LockStatus`], are copied from the one the
+    /// last frame wrote to. Fails with [`Fsr2WgpuError::NotYetDispatched`] before the first frame.
+    pub fn debug_resource(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        resource: Fsr2DebugResource,
+    ) -> Result<Fsr2Texture<'_>, Fsr2WgpuError> {
+        if self.last_input_resolution.is_none() {
+            return Err(Fsr2WgpuError::NotYetDispatched);
+        }
+
+        let size = match resource {
+            Fsr2DebugResource::AutoExposure => UVec2::ONE,
+            _ if resource.is_display_resolution() => self.descriptor.upscaled_resolution,
+            _ => self.descriptor.max_input_resolution,
+        };
+
+        let device = &self.device;
+        let texture = self
+            .debug_visualizer
+            .get_or_insert_with(|| DebugVisualizer::new(device))
+            .texture(device, resource, size);
+
+        unsafe {
+            command_encoder.transition_textures(&[(
+                texture.texture,
+                TextureUses::COPY_DST,
+                TextureSelector {
+                    mips: 0..1,
+                    layers: 0..1,
+                },
+            )]);
+
+            let raw_device = self
+                .device
+                .as_hal::<Vulkan, _, _>(|device| device.unwrap().raw_device().clone());
+            let command_buffer = command_encoder
+                .as_hal_mut::<Vulkan, _, _>(|cmd_encoder| cmd_encoder.unwrap().raw_handle());
+
+            copy_internal_image_to_image(
+                &mut self.context,
+                &raw_device,
+                command_buffer,
+                resource.resource_id(),
+                size,
+                texture
+                    .texture
+                    .as_hal::<Vulkan, _, _>(|texture| texture.unwrap().raw_handle()),
+            );
+        }
+
+        Ok(texture)
+    }
+
+    /// Draw a visualization of one of FSR's internal resources over the whole of `target`.
+    pub fn debug_visualize(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        resource: Fsr2DebugResource,
+        target: &TextureView,
+        target_format: TextureFormat,
+    ) -> Result<(), Fsr2WgpuError> {
+        self.debug_resource(command_encoder, resource)?;
+
+        let valid_size = match resource {
+            Fsr2DebugResource::AutoExposure => UVec2::ONE,
+            _ if resource.is_display_resolution() => self.descriptor.upscaled_resolution,
+            _ => self.last_input_resolution.unwrap(),
+        };
+
+        self.debug_visualizer.as_mut().unwrap().visualize(
+            &self.device,
+            command_encoder,
+            resource,
+            valid_size,
+            target,
+            target_format,
+        );
+
+        Ok(())
     }
 }
 
@@ -341,12 +1146,105 @@ impl<D: Deref<Target = Device>> Drop for Fsr2Context<D> {
                     .expect("Failed to wait for idle device when destroying Fsr2Context");
             });
 
-            ffx_check_result(ffxFsr2ContextDestroy(&mut self.context as *mut _))
+            ffx_check_result(ffxFsr2ContextDestroy(&mut *self.context as *mut _))
                 .expect("Failed to destroy Fsr2Context");
         }
     }
 }
 
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fsr2ContextDescriptor {
+    pub max_input_resolution: UVec2,
+    pub upscaled_resolution: UVec2,
+    pub initialization_flags: Fsr2InitializationFlags,
+    /// Allow multisampled color and depth inputs, by resolving them before they're passed to FSR.
+    pub msaa_resolve: bool,
+    /// Have the context allocate its own output textures of this format, double buffered, to be
+    /// used when [`Fsr2RenderParameters::output`] is `None`.
+    pub managed_output_format: Option<TextureFormat>,
+    /// Reset FSR's history whenever consecutive frames differ by more than these thresholds, in
+    /// addition to when [`Fsr2RenderParameters::reset`] is set.
+    pub reset_detection: Option<Fsr2ResetThresholds>,
+    /// Check that the color input matches this color space, and decode and encode the color input
+    /// and output around FSR if it's encoded. Output textures then need
+    /// [`wgpu::TextureUsages::RENDER_ATTACHMENT`] instead of storage usage.
+    pub color_space: Option<Fsr2ColorSpace>,
+    /// Dispatch FSR on a queue of another family than the one rendering its inputs and reading
+    /// its output. wgpu only creates a single queue, of the graphics family, so the device needs
+    /// creating through ash and wgpu-hal, with queues of both families:
+    ///
+    /// 1. Pick the families with [`Fsr2QueueFamilies::from_adapter`].
+    /// 2. Create the `ash::Device` with a queue of each family, and the extensions and features
+    ///    of `wgpu_hal::vulkan::Adapter::required_device_extensions` and
+    ///    `physical_device_features`.
+    /// 3. Wrap it with `wgpu_hal::vulkan::Adapter::device_from_raw`, given the graphics family,
+    ///    and [`wgpu::Adapter::create_device_from_hal`], and get the compute queue with
+    ///    `vkGetDeviceQueue`.
+    ///
+    /// Each frame is then recorded as:
+    ///
+    /// 1. Graphics: render the inputs and [`Fsr2Context::release_to_compute`], then submit.
+    /// 2. Compute: [`Fsr2Context::render_on_compute`] into a command buffer allocated from a
+    ///    command pool of the compute family, then submit it once the graphics submission has
+    ///    completed.
+    /// 3. Graphics: once the compute submission has completed,
+    ///    [`Fsr2Context::acquire_from_compute`] and read the output, then submit.
+    ///
+    /// wgpu can't wait on or signal semaphores, so the submissions are ordered on the host, e.g.
+    /// with [`wgpu::Device::poll`] and a fence, unless the graphics side is submitted through
+    /// wgpu-hal too. [`Fsr2Context::render`] returns
+    /// [`Fsr2WgpuError::AsyncComputeEncoder`], as its command encoder can only be of the graphics
+    /// family. When both families are the same, everything stays on that queue, no transfers are
+    /// recorded, and either method can be used.
+    pub queue_families: Option<Fsr2QueueFamilies>,
+}
+
+impl Fsr2ContextDescriptor {
+    pub fn new(max_input_resolution: UVec2, upscaled_resolution: UVec2) -> Self {
+        Self {
+            max_input_resolution,
+            upscaled_resolution,
+            initialization_flags: Fsr2InitializationFlags::empty(),
+            msaa_resolve: false,
+            managed_output_format: None,
+            reset_detection: None,
+            color_space: None,
+            queue_families: None,
+        }
+    }
+
+    pub fn initialization_flags(mut self, initialization_flags: Fsr2InitializationFlags) -> Self {
+        self.initialization_flags = initialization_flags;
+        self
+    }
+
+    pub fn msaa_resolve(mut self, msaa_resolve: bool) -> Self {
+        self.msaa_resolve = msaa_resolve;
+        self
+    }
+
+    pub fn managed_output_format(mut self, managed_output_format: TextureFormat) -> Self {
+        self.managed_output_format = Some(managed_output_format);
+        self
+    }
+
+    pub fn reset_detection(mut self, thresholds: Fsr2ResetThresholds) -> Self {
+        self.reset_detection = Some(thresholds);
+        self
+    }
+
+    pub fn color_space(mut self, color_space: Fsr2ColorSpace) -> Self {
+        self.color_space = Some(color_space);
+        self
+    }
+
+    pub fn queue_families(mut self, queue_families: Fsr2QueueFamilies) -> Self {
+        self.queue_families = Some(queue_families);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct Fsr2RenderParameters<'a> {
     pub color: Fsr2Texture<'a>,
     pub depth: Fsr2Texture<'a>,
@@ -355,17 +1253,285 @@ pub struct Fsr2RenderParameters<'a> {
     pub exposure: Fsr2Exposure<'a>,
     pub reactive_mask: Fsr2ReactiveMask<'a>,
     pub transparency_and_composition_mask: Option<Fsr2Texture<'a>>,
-    pub output: Fsr2Texture<'a>,
+    pub output: Option<Fsr2Texture<'a>>,
+    pub input_resolution: UVec2,
+    /// Top left corner of the `input_resolution` sized region to read from the color, depth,
+    /// motion vector and mask textures, e.g. one player's view in split-screen. Non zero offsets
+    /// cost a copy of each input, so those textures then need [`wgpu::TextureUsages::COPY_SRC`].
+    pub input_offset: UVec2,
+    /// Top left corner of the upscaled resolution sized region of [`Fsr2RenderParameters::output`]
+    /// to write to. Non zero offsets cost a copy of the output, which then needs
+    /// [`wgpu::TextureUsages::COPY_DST`]. Ignored for managed outputs.
+    pub output_offset: UVec2,
+    pub sharpness: f32,
+    /// Time elapsed since the previous frame, or `None` to have the context measure it.
+    pub frame_delta_time: Option<Duration>,
+    pub reset: bool,
+    pub camera_near: f32,
+    pub camera_far: Option<f32>,
+    pub camera_fov_angle_vertical: f32,
+    /// The camera's world to view space transform, used by
+    /// [`Fsr2ContextDescriptor::reset_detection`] to detect camera cuts.
+    pub camera_view: Option<Mat4>,
+    pub jitter_offset: Vec2,
+}
+
+impl Fsr2RenderParameters<'_> {
+    pub fn snapshot(&self) -> Fsr2RenderParametersSnapshot {
+        self.into()
+    }
+}
+
+/// The texture-free part of [`Fsr2RenderParameters`], e.g. for logging what went into a frame.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fsr2RenderParametersSnapshot {
+    pub motion_vector_scale: Option<Vec2>,
+    pub exposure: Fsr2ExposureMode,
     pub input_resolution: UVec2,
     pub sharpness: f32,
+    /// The frame delta time given to FSR. Snapshots taken with [`Fsr2RenderParameters::snapshot`]
+    /// hold zero here when the context was left to measure it.
     pub frame_delta_time: Duration,
     pub reset: bool,
     pub camera_near: f32,
     pub camera_far: Option<f32>,
     pub camera_fov_angle_vertical: f32,
+    pub camera_view: Option<Mat4>,
     pub jitter_offset: Vec2,
-    pub adapter: &'a Adapter,
-    pub command_encoder: &'a mut CommandEncoder,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fsr2ExposureMode {
+    AutoExposure,
+    ManualExposure { pre_exposure: f32 },
+}
+
+impl From<&Fsr2RenderParameters<'_>> for Fsr2RenderParametersSnapshot {
+    fn from(parameters: &Fsr2RenderParameters<'_>) -> Self {
+        Self {
+            motion_vector_scale: parameters.motion_vector_scale,
+            exposure: match parameters.exposure {
+                Fsr2Exposure::AutoExposure => Fsr2ExposureMode::AutoExposure,
+                Fsr2Exposure::ManualExposure { pre_exposure, .. } => {
+                    Fsr2ExposureMode::ManualExposure { pre_exposure }
+                }
+            },
+            input_resolution: parameters.input_resolution,
+            sharpness: parameters.sharpness,
+            frame_delta_time: parameters.frame_delta_time.unwrap_or_default(),
+            reset: parameters.reset,
+            camera_near: parameters.camera_near,
+            camera_far: parameters.camera_far,
+            camera_fov_angle_vertical: parameters.camera_fov_angle_vertical,
+            camera_view: parameters.camera_view,
+            jitter_offset: parameters.jitter_offset,
+        }
+    }
+}
+
+unsafe fn input_texture_to_ffx_resource<'a>(
+    context: &mut FfxFsr2Context,
+    texture: Option<Fsr2Texture<'a>>,
+    new_use: TextureUses,
+    resource_state: FfxResourceStates,
+    layer: u32,
+    texture_uses: &mut ArrayVec<(&'a Texture, TextureUses, TextureSelector), 7>,
+    formats: &FormatTable,
+) -> Result<FfxResource, Fsr2WgpuError> {
+    let resource = match texture {
+        Some(Fsr2Texture { texture, view }) => {
+            // Textures without layers, like exposure or internal ones, are shared by every layer
+            let layer = if texture.depth_or_array_layers() > 1 {
+                layer
+            } else {
+                0
+            };
+            texture_uses.push((
+                texture,
+                new_use,
+                TextureSelector {
+                    mips: 0..1,
+                    layers: layer..layer + 1,
+                },
+            ));
+
+            ffxGetTextureResourceVK(
+                context as *mut _,
+                texture.as_hal::<Vulkan, _, _>(|texture| texture.unwrap().raw_handle()),
+                view.as_hal::<Vulkan, _, _>(|view| view.unwrap().raw_handle()),
+                texture.width(),
+                texture.height(),
+                formats
+                    .get(texture.format())
+                    .ok_or(Fsr2WgpuError::UnsupportedFormat(texture.format()))?,
+                ptr::null_mut(),
+                resource_state,
+            )
+        }
+        None => ffxGetTextureResourceVK(
+            context as *mut _,
+            Image::null(),
+            ImageView::null(),
+            1,
+            1,
+            Format::UNDEFINED,
+            ptr::null_mut(),
+            resource_state,
+        ),
+    };
+
+    Ok(resource)
+}
+
+/// The state of [`Fsr2Context`] that carries over from one frame to the next.
+struct FrameHistory {
+    frame_clock: FrameClock,
+    reset_detector: Option<ResetDetector>,
+}
+
+/// The texture FSR reads the reactive mask from, if any.
+fn reactive_mask_texture(reactive_mask: Fsr2ReactiveMask) -> Option<Fsr2Texture> {
+    match reactive_mask {
+        Fsr2ReactiveMask::NoMask => None,
+        Fsr2ReactiveMask::ManualMask(mask) => Some(mask),
+        #[allow(unused_variables)]
+        Fsr2ReactiveMask::AutoMask {
+            color_opaque_only,
+            color_opaque_and_transparent,
+            scale,
+            threshold,
+            binary_value,
+            flags,
+        } => {
+            todo!()
+        }
+    }
+}
+
+/// The textures read and written by a single dispatch, once they're ready for FSR. Generic over
+/// the texture, so that the translation to FFX resources can be tested without a GPU.
+pub(crate) struct DispatchInputs<T> {
+    pub color: T,
+    pub depth: T,
+    pub motion_vectors: T,
+    pub exposure: Option<T>,
+    pub reactive_mask: Option<T>,
+    pub transparency_and_composition_mask: Option<T>,
+    pub output: T,
+}
+
+impl<T> DispatchInputs<T> {
+    /// Translate every input into an FFX resource with `to_resource`, given its texture (or
+    /// `None` for a null resource), the use wgpu has to transition it to, and the state FSR
+    /// expects it in.
+    pub fn into_resources<E, F>(self, mut to_resource: F) -> Result<DispatchResources, E>
+    where
+        F: FnMut(Option<T>, TextureUses, FfxResourceStates) -> Result<FfxResource, E>,
+    {
+        Ok(DispatchResources {
+            color: to_resource(
+                Some(self.color),
+                TextureUses::RESOURCE,
+                FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
+            )?,
+            depth: to_resource(
+                Some(self.depth),
+                TextureUses::RESOURCE, // TODO: Needs to be SHADER_READ_ONLY, not depth stencil
+                FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
+            )?,
+            motion_vectors: to_resource(
+                Some(self.motion_vectors),
+                TextureUses::RESOURCE,
+                FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
+            )?,
+            exposure: to_resource(
+                self.exposure,
+                TextureUses::RESOURCE,
+                FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
+            )?,
+            reactive: to_resource(
+                self.reactive_mask,
+                TextureUses::RESOURCE,
+                FfxResourceStates_FFX_RESOURCE_STATE_GENERIC_READ,
+            )?,
+            transparency_and_composition: to_resource(
+                self.transparency_and_composition_mask,
+                TextureUses::RESOURCE,
+                FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
+            )?,
+            output: to_resource(
+                Some(self.output),
+                TextureUses::RESOURCE, // TODO: Needs to be GENERAL, not SHADER_READ_ONLY
+                FfxResourceStates_FFX_RESOURCE_STATE_UNORDERED_ACCESS,
+            )?,
+        })
+    }
+}
+
+/// The FFX resources read and written by a single dispatch.
+pub(crate) struct DispatchResources {
+    pub color: FfxResource,
+    pub depth: FfxResource,
+    pub motion_vectors: FfxResource,
+    pub exposure: FfxResource,
+    pub reactive: FfxResource,
+    pub transparency_and_composition: FfxResource,
+    pub output: FfxResource,
+}
+
+/// The description passed to `ffxFsr2ContextCreate`.
+pub(crate) fn context_description(
+    flags: Fsr2InitializationFlags,
+    max_input_resolution: UVec2,
+    upscaled_resolution: UVec2,
+    callbacks: FfxFsr2Interface,
+    device: FfxDevice,
+) -> FfxFsr2ContextDescription {
+    FfxFsr2ContextDescription {
+        flags: flags.bits() as u32,
+        maxRenderSize: uvec2_to_dim2d(max_input_resolution),
+        displaySize: uvec2_to_dim2d(upscaled_resolution),
+        callbacks,
+        device,
+    }
+}
+
+/// Translate render parameters into the description passed to `ffxFsr2ContextDispatch`.
+pub(crate) fn dispatch_description(
+    command_list: FfxCommandList,
+    resources: DispatchResources,
+    parameters: &Fsr2RenderParametersSnapshot,
+) -> FfxFsr2DispatchDescription {
+    let pre_exposure = match parameters.exposure {
+        Fsr2ExposureMode::AutoExposure => 1.0,
+        Fsr2ExposureMode::ManualExposure { pre_exposure } => pre_exposure,
+    };
+
+    FfxFsr2DispatchDescription {
+        commandList: command_list,
+        color: resources.color,
+        depth: resources.depth,
+        motionVectors: resources.motion_vectors,
+        exposure: resources.exposure,
+        reactive: resources.reactive,
+        transparencyAndComposition: resources.transparency_and_composition,
+        output: resources.output,
+        jitterOffset: vec2_to_float_coords2d(parameters.jitter_offset),
+        motionVectorScale: vec2_to_float_coords2d(
+            parameters.motion_vector_scale.unwrap_or(Vec2::ONE),
+        ),
+        renderSize: uvec2_to_dim2d(parameters.input_resolution),
+        enableSharpening: parameters.sharpness > 0.0,
+        sharpness: parameters.sharpness.clamp(0.0, 1.0),
+        frameTimeDelta: parameters.frame_delta_time.as_secs_f32() * 1000.0,
+        preExposure: pre_exposure,
+        reset: parameters.reset,
+        cameraNear: parameters.camera_near,
+        cameraFar: parameters.camera_far.unwrap_or(0.0),
+        cameraFovAngleVertical: parameters.camera_fov_angle_vertical,
+    }
 }
 
 fn uvec2_to_dim2d(vec: UVec2) -> FfxDimensions2D {
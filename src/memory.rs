@@ -0,0 +1,200 @@
+use crate::fsr::{
+    ffxGetVkImage, FfxFsr2Context, FFX_FSR2_RESOURCE_IDENTIFIER_AUTO_EXPOSURE,
+    FFX_FSR2_RESOURCE_IDENTIFIER_DEBUG_OUTPUT, FFX_FSR2_RESOURCE_IDENTIFIER_DEPTH_CLIP,
+    FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_DEPTH,
+    FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_MOTION_VECTORS,
+    FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_REACTIVE_MASKS,
+    FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_DEFAULT_EXPOSURE,
+    FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_DEFAULT_REACTIVITY,
+    FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_DEFAULT_TRANSPARENCY_AND_COMPOSITION,
+    FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_UPSCALED_COLOR_1,
+    FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_UPSCALED_COLOR_2,
+    FFX_FSR2_RESOURCE_IDENTIFIER_LANCZOS_LUT, FFX_FSR2_RESOURCE_IDENTIFIER_LOCK_STATUS_1,
+    FFX_FSR2_RESOURCE_IDENTIFIER_LOCK_STATUS_2, FFX_FSR2_RESOURCE_IDENTIFIER_LUMA_HISTORY,
+    FFX_FSR2_RESOURCE_IDENTIFIER_PREPARED_INPUT_COLOR, FFX_FSR2_RESOURCE_IDENTIFIER_RCAS_INPUT,
+    FFX_FSR2_RESOURCE_IDENTIFIER_RECONSTRUCTED_PREVIOUS_NEAREST_DEPTH,
+    FFX_FSR2_RESOURCE_IDENTIFIER_SPD_ATOMIC_COUNT,
+    FFX_FSR2_RESOURCE_IDENTITIER_UPSAMPLE_MAXIMUM_BIAS_LUT,
+};
+use crate::Fsr2Context;
+use ash::vk::Image;
+use std::ops::Deref;
+use wgpu::Device;
+use wgpu_core::api::Vulkan;
+
+/// The resources FSR creates for itself. Identifiers that only alias these (e.g. the lock status
+/// and upscaled color of the current frame, or the auto exposure mips) are left out, as are the
+/// inputs and output registered on every dispatch.
+const INTERNAL_RESOURCES: &[(u32, &str)] = &[
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_RECONSTRUCTED_PREVIOUS_NEAREST_DEPTH,
+        "reconstructed_previous_nearest_depth",
+    ),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_MOTION_VECTORS,
+        "dilated_motion_vectors",
+    ),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_DEPTH, "dilated_depth"),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_DEPTH_CLIP, "depth_clip"),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_PREPARED_INPUT_COLOR,
+        "prepared_input_color",
+    ),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_LUMA_HISTORY, "luma_history"),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_DEBUG_OUTPUT, "debug_output"),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_LANCZOS_LUT, "lanczos_lut"),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_SPD_ATOMIC_COUNT,
+        "spd_atomic_count",
+    ),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_RCAS_INPUT, "rcas_input"),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_LOCK_STATUS_1, "lock_status_1"),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_LOCK_STATUS_2, "lock_status_2"),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_UPSCALED_COLOR_1,
+        "internal_upscaled_color_1",
+    ),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_UPSCALED_COLOR_2,
+        "internal_upscaled_color_2",
+    ),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_DEFAULT_REACTIVITY,
+        "internal_default_reactivity",
+    ),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_DEFAULT_TRANSPARENCY_AND_COMPOSITION,
+        "internal_default_transparency_and_composition",
+    ),
+    (
+        FFX_FSR2_RESOURCE_IDENTITIER_UPSAMPLE_MAXIMUM_BIAS_LUT,
+        "upsample_maximum_bias_lut",
+    ),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_DILATED_REACTIVE_MASKS,
+        "dilated_reactive_masks",
+    ),
+    (FFX_FSR2_RESOURCE_IDENTIFIER_AUTO_EXPOSURE, "auto_exposure"),
+    (
+        FFX_FSR2_RESOURCE_IDENTIFIER_INTERNAL_DEFAULT_EXPOSURE,
+        "internal_default_exposure",
+    ),
+];
+
+/// Device memory held by FSR's internal resources. See [`Fsr2Context::memory_usage`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Fsr2MemoryUsage {
+    pub resources: Vec<Fsr2ResourceMemoryUsage>,
+    /// Bytes across all of `resources`.
+    pub total: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fsr2ResourceMemoryUsage {
+    pub name: &'static str,
+    pub bytes: u64,
+}
+
+/// A device memory allocation FSR is about to make. See [`Fsr2AllocationCallbacks`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fsr2Allocation {
+    pub size: u64,
+    /// Index into the physical device's memory types, e.g. to tell device local allocations for
+    /// internal resources apart from the host visible ones FSR uses for its constant buffers.
+    pub memory_type_index: u32,
+}
+
+/// Told about every device memory allocation FSR makes, e.g. to count it against a budget.
+///
+/// FSR binds each of its resources to the start of a dedicated allocation, so memory can't be
+/// sub-allocated from an outside allocator. Allocations can however be refused, which fails the
+/// creation of the context with [`crate::Fsr2Error::BackendApiError`].
+///
+/// Set with [`crate::Fsr2Device::set_allocation_callbacks`], and only called for contexts created
+/// through that device.
+pub trait Fsr2AllocationCallbacks: Send + Sync {
+    /// Return `false` to refuse the allocation.
+    fn allocate(&self, allocation: &Fsr2Allocation) -> bool;
+
+    /// Called once for every allocation that was accepted, when the context is dropped.
+    fn free(&self, allocation: &Fsr2Allocation);
+}
+
+impl<D: Deref<Target = Device>> Fsr2Context<D> {
+    /// Device memory held by each of FSR's internal resources, and in total.
+    ///
+    /// Textures this crate creates around FSR, e.g. to resolve MSAA inputs or for managed outputs,
+    /// are wgpu textures, and so aren't included.
+    pub fn memory_usage(&self) -> Fsr2MemoryUsage {
+        unsafe {
+            self.device.as_hal::<Vulkan, _, _>(|hal_device| {
+                let raw_device = hal_device.unwrap().raw_device();
+                memory_usage(internal_images(&self.context), |image| {
+                    raw_device.get_image_memory_requirements(image).size
+                })
+            })
+        }
+    }
+}
+
+fn internal_images(context: &FfxFsr2Context) -> impl Iterator<Item = (&'static str, Image)> + '_ {
+    INTERNAL_RESOURCES.iter().map(move |&(resource_id, name)| {
+        // Only reads the context, despite taking it mutably
+        let image = unsafe { ffxGetVkImage(context as *const _ as *mut _, resource_id) };
+        (name, image)
+    })
+}
+
+fn memory_usage(
+    images: impl Iterator<Item = (&'static str, Image)>,
+    mut image_size: impl FnMut(Image) -> u64,
+) -> Fsr2MemoryUsage {
+    let mut counted = Vec::new();
+    let mut usage = Fsr2MemoryUsage::default();
+    for (name, image) in images {
+        // Resources that weren't created are null, and some identifiers share an image
+        if image == Image::null() || counted.contains(&image) {
+            continue;
+        }
+        counted.push(image);
+
+        let bytes = image_size(image);
+        usage
+            .resources
+            .push(Fsr2ResourceMemoryUsage { name, bytes });
+        usage.total += bytes;
+    }
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    #[test]
+    fn images_are_counted_once() {
+        let images = [
+            ("a", Image::from_raw(1)),
+            ("b", Image::null()),
+            ("c", Image::from_raw(2)),
+            ("d", Image::from_raw(1)),
+        ];
+        let usage = memory_usage(images.into_iter(), |image| image.as_raw() * 1024);
+
+        assert_eq!(
+            usage.resources,
+            [
+                Fsr2ResourceMemoryUsage {
+                    name: "a",
+                    bytes: 1024
+                },
+                Fsr2ResourceMemoryUsage {
+                    name: "c",
+                    bytes: 2048
+                },
+            ]
+        );
+        assert_eq!(usage.total, 3072);
+    }
+}
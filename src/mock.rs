@@ -0,0 +1,546 @@
+//! An FFX backend that records the calls FSR makes instead of talking to a GPU, so that the way
+//! this crate drives FSR can be unit tested on the CPU.
+
+use crate::fsr::{
+    ffxFsr2ContextCreate, ffxFsr2ContextDestroy, ffxFsr2ContextDispatch, ffx_check_result,
+    FfxCommandList, FfxCreateResourceDescription, FfxDevice, FfxDeviceCapabilities, FfxErrorCode,
+    FfxFsr2Context, FfxFsr2Interface, FfxFsr2Pass, FfxGpuJobDescription, FfxPipelineDescription,
+    FfxPipelineState, FfxResource, FfxResourceDescription, FfxResourceInternal,
+    FfxShaderModel_FFX_SHADER_MODEL_6_5, FFX_OK,
+};
+use crate::{
+    context_description, dispatch_description, DispatchResources, Fsr2Error,
+    Fsr2InitializationFlags, Fsr2RenderParametersSnapshot,
+};
+use glam::UVec2;
+use std::ffi::c_void;
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+
+/// `FFX_FSR2_CONSTANTBUFFER_IDENTIFIER_FSR2`, which isn't part of the public headers.
+const FSR2_CONSTANT_BUFFER_IDENTIFIER: u32 = 0;
+
+/// Everything FSR asked the backend to do.
+#[derive(Default)]
+pub(crate) struct MockBackend {
+    /// Internal resources, as `(resource identifier, description)`.
+    pub created_resources: Vec<(u32, FfxResourceDescription)>,
+    /// External resources, in the order they were registered.
+    pub registered_resources: Vec<FfxResource>,
+    /// The context flags of every pipeline created, as FSR picks shader permutations by them.
+    pub pipeline_context_flags: Vec<u32>,
+    pub scheduled_jobs: Vec<FfxGpuJobDescription>,
+    pub executed_job_batches: u32,
+    descriptions: Vec<FfxResourceDescription>,
+}
+
+impl MockBackend {
+    fn interface(&mut self) -> FfxFsr2Interface {
+        FfxFsr2Interface {
+            fpCreateBackendContext: Some(create_backend_context),
+            fpGetDeviceCapabilities: Some(get_device_capabilities),
+            fpDestroyBackendContext: Some(destroy_backend_context),
+            fpCreateResource: Some(create_resource),
+            fpRegisterResource: Some(register_resource),
+            fpUnregisterResources: Some(unregister_resources),
+            fpGetResourceDescription: Some(get_resource_description),
+            fpDestroyResource: Some(destroy_resource),
+            fpCreatePipeline: Some(create_pipeline),
+            fpDestroyPipeline: Some(destroy_pipeline),
+            fpScheduleGpuJob: Some(schedule_gpu_job),
+            fpExecuteGpuJobs: Some(execute_gpu_jobs),
+            scratchBuffer: self as *mut Self as *mut c_void,
+            scratchBufferSize: mem::size_of::<Self>(),
+        }
+    }
+
+    fn add_description(&mut self, description: FfxResourceDescription) -> FfxResourceInternal {
+        self.descriptions.push(description);
+        FfxResourceInternal {
+            internalIndex: self.descriptions.len() as i32 - 1,
+        }
+    }
+}
+
+/// An FSR context running on a [`MockBackend`].
+pub(crate) struct MockContext {
+    context: Box<FfxFsr2Context>,
+    // Boxed so that the pointer handed to FSR stays valid when the MockContext moves
+    backend: Box<MockBackend>,
+}
+
+impl MockContext {
+    pub fn new(
+        flags: Fsr2InitializationFlags,
+        max_input_resolution: UVec2,
+        upscaled_resolution: UVec2,
+    ) -> Result<Self, Fsr2Error> {
+        let mut backend = Box::<MockBackend>::default();
+        let context_description = context_description(
+            flags,
+            max_input_resolution,
+            upscaled_resolution,
+            backend.interface(),
+            ptr::null_mut(),
+        );
+
+        unsafe {
+            let context = Box::into_raw(Box::new(MaybeUninit::<FfxFsr2Context>::uninit()));
+            let result = ffxFsr2ContextCreate((*context).as_mut_ptr(), &context_description);
+            let context = Box::from_raw(context as *mut FfxFsr2Context);
+            ffx_check_result(result)?;
+
+            Ok(Self { context, backend })
+        }
+    }
+
+    pub fn backend(&self) -> &MockBackend {
+        &self.backend
+    }
+
+    pub fn dispatch(
+        &mut self,
+        resources: DispatchResources,
+        parameters: &Fsr2RenderParametersSnapshot,
+    ) -> Result<(), Fsr2Error> {
+        let command_list: FfxCommandList = ptr::NonNull::dangling().as_ptr();
+        let description = dispatch_description(command_list, resources, parameters);
+        unsafe { ffx_check_result(ffxFsr2ContextDispatch(&mut *self.context, &description)) }
+    }
+}
+
+impl Drop for MockContext {
+    fn drop(&mut self) {
+        unsafe {
+            ffxFsr2ContextDestroy(&mut *self.context);
+        }
+    }
+}
+
+unsafe fn backend<'a>(interface: *mut FfxFsr2Interface) -> &'a mut MockBackend {
+    &mut *((*interface).scratchBuffer as *mut MockBackend)
+}
+
+unsafe extern "C" fn create_backend_context(
+    _interface: *mut FfxFsr2Interface,
+    _device: FfxDevice,
+) -> FfxErrorCode {
+    FFX_OK
+}
+
+unsafe extern "C" fn get_device_capabilities(
+    _interface: *mut FfxFsr2Interface,
+    capabilities: *mut FfxDeviceCapabilities,
+    _device: FfxDevice,
+) -> FfxErrorCode {
+    *capabilities = FfxDeviceCapabilities {
+        minimumSupportedShaderModel: FfxShaderModel_FFX_SHADER_MODEL_6_5,
+        waveLaneCountMin: 32,
+        waveLaneCountMax: 64,
+        fp16Supported: true,
+        raytracingSupported: false,
+    };
+    FFX_OK
+}
+
+unsafe extern "C" fn destroy_backend_context(_interface: *mut FfxFsr2Interface) -> FfxErrorCode {
+    FFX_OK
+}
+
+unsafe extern "C" fn create_resource(
+    interface: *mut FfxFsr2Interface,
+    description: *const FfxCreateResourceDescription,
+    resource: *mut FfxResourceInternal,
+) -> FfxErrorCode {
+    let backend = backend(interface);
+    let description = &*description;
+    backend
+        .created_resources
+        .push((description.id, description.resourceDescription));
+    *resource = backend.add_description(description.resourceDescription);
+    FFX_OK
+}
+
+unsafe extern "C" fn register_resource(
+    interface: *mut FfxFsr2Interface,
+    in_resource: *const FfxResource,
+    out_resource: *mut FfxResourceInternal,
+) -> FfxErrorCode {
+    let backend = backend(interface);
+    backend.registered_resources.push(*in_resource);
+    *out_resource = backend.add_description((*in_resource).description);
+    FFX_OK
+}
+
+unsafe extern "C" fn unregister_resources(_interface: *mut FfxFsr2Interface) -> FfxErrorCode {
+    FFX_OK
+}
+
+unsafe extern "C" fn get_resource_description(
+    interface: *mut FfxFsr2Interface,
+    resource: FfxResourceInternal,
+) -> FfxResourceDescription {
+    backend(interface).descriptions[resource.internalIndex as usize]
+}
+
+unsafe extern "C" fn destroy_resource(
+    _interface: *mut FfxFsr2Interface,
+    _resource: FfxResourceInternal,
+) -> FfxErrorCode {
+    FFX_OK
+}
+
+unsafe extern "C" fn create_pipeline(
+    interface: *mut FfxFsr2Interface,
+    _pass: FfxFsr2Pass,
+    description: *const FfxPipelineDescription,
+    pipeline: *mut FfxPipelineState,
+) -> FfxErrorCode {
+    backend(interface)
+        .pipeline_context_flags
+        .push((*description).contextFlags);
+
+    // A pipeline reading only FSR's own constants, so that they reach the scheduled jobs
+    let mut state: FfxPipelineState = mem::zeroed();
+    state.constCount = 1;
+    state.cbResourceBindings[0].resourceIdentifier = FSR2_CONSTANT_BUFFER_IDENTIFIER;
+    *pipeline = state;
+    FFX_OK
+}
+
+unsafe extern "C" fn destroy_pipeline(
+    _interface: *mut FfxFsr2Interface,
+    _pipeline: *mut FfxPipelineState,
+) -> FfxErrorCode {
+    FFX_OK
+}
+
+unsafe extern "C" fn schedule_gpu_job(
+    interface: *mut FfxFsr2Interface,
+    job: *const FfxGpuJobDescription,
+) -> FfxErrorCode {
+    backend(interface).scheduled_jobs.push(*job);
+    FFX_OK
+}
+
+unsafe extern "C" fn execute_gpu_jobs(
+    interface: *mut FfxFsr2Interface,
+    _command_list: FfxCommandList,
+) -> FfxErrorCode {
+    backend(interface).executed_job_batches += 1;
+    FFX_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_clock::frame_delta_time;
+    use crate::fsr::{
+        FfxFsr2InitializationFlagBits_FFX_FSR2_ENABLE_AUTO_EXPOSURE,
+        FfxFsr2InitializationFlagBits_FFX_FSR2_ENABLE_DEPTH_INVERTED,
+        FfxFsr2InitializationFlagBits_FFX_FSR2_ENABLE_HIGH_DYNAMIC_RANGE,
+        FfxGpuJobType_FFX_GPU_JOB_COMPUTE, FfxResourceFlags_FFX_RESOURCE_FLAGS_NONE,
+        FfxResourceStates, FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ,
+        FfxResourceStates_FFX_RESOURCE_STATE_GENERIC_READ,
+        FfxResourceStates_FFX_RESOURCE_STATE_UNORDERED_ACCESS,
+        FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE2D, FfxSurfaceFormat,
+        FfxSurfaceFormat_FFX_SURFACE_FORMAT_R16G16B16A16_FLOAT,
+        FfxSurfaceFormat_FFX_SURFACE_FORMAT_R16G16_FLOAT,
+        FfxSurfaceFormat_FFX_SURFACE_FORMAT_R32_FLOAT,
+        FfxSurfaceFormat_FFX_SURFACE_FORMAT_R8_UNORM,
+    };
+    use crate::{DispatchInputs, Fsr2ExposureMode};
+    use glam::Vec2;
+    use std::convert::Infallible;
+    use std::time::Duration;
+    use wgpu_hal::TextureUses;
+
+    const INPUT_RESOLUTION: UVec2 = UVec2::new(64, 64);
+    const UPSCALED_RESOLUTION: UVec2 = UVec2::new(128, 128);
+    const EXPOSURE: MockTexture = MockTexture {
+        address: 0xE000,
+        size: UVec2::ONE,
+        format: FfxSurfaceFormat_FFX_SURFACE_FORMAT_R32_FLOAT,
+    };
+    const REACTIVE_MASK: MockTexture = MockTexture {
+        address: 0xD000,
+        size: INPUT_RESOLUTION,
+        format: FfxSurfaceFormat_FFX_SURFACE_FORMAT_R8_UNORM,
+    };
+
+    /// A texture FSR will treat as a real one, which the mock never dereferences.
+    #[derive(Clone, Copy)]
+    struct MockTexture {
+        address: usize,
+        size: UVec2,
+        format: FfxSurfaceFormat,
+    }
+
+    fn context(flags: Fsr2InitializationFlags) -> MockContext {
+        MockContext::new(flags, INPUT_RESOLUTION, UPSCALED_RESOLUTION).unwrap()
+    }
+
+    /// The counterpart of `input_texture_to_ffx_resource` for [`MockTexture`]s.
+    fn to_resource(
+        texture: Option<MockTexture>,
+        _new_use: TextureUses,
+        state: FfxResourceStates,
+    ) -> Result<FfxResource, Infallible> {
+        let mut resource: FfxResource = unsafe { mem::zeroed() };
+        if let Some(texture) = texture {
+            resource.resource = texture.address as *mut c_void;
+            resource.description = FfxResourceDescription {
+                type_: FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE2D,
+                format: texture.format,
+                width: texture.size.x,
+                height: texture.size.y,
+                depth: 1,
+                mipCount: 1,
+                flags: FfxResourceFlags_FFX_RESOURCE_FLAGS_NONE,
+            };
+        }
+        resource.state = state;
+        Ok(resource)
+    }
+
+    fn inputs(
+        exposure: Option<MockTexture>,
+        reactive_mask: Option<MockTexture>,
+    ) -> DispatchInputs<MockTexture> {
+        let input = |address, format| MockTexture {
+            address,
+            size: INPUT_RESOLUTION,
+            format,
+        };
+
+        DispatchInputs {
+            color: input(
+                0xA000,
+                FfxSurfaceFormat_FFX_SURFACE_FORMAT_R16G16B16A16_FLOAT,
+            ),
+            depth: input(0xB000, FfxSurfaceFormat_FFX_SURFACE_FORMAT_R32_FLOAT),
+            motion_vectors: input(0xC000, FfxSurfaceFormat_FFX_SURFACE_FORMAT_R16G16_FLOAT),
+            exposure,
+            reactive_mask,
+            transparency_and_composition_mask: None,
+            output: MockTexture {
+                address: 0xF000,
+                size: UPSCALED_RESOLUTION,
+                format: FfxSurfaceFormat_FFX_SURFACE_FORMAT_R16G16B16A16_FLOAT,
+            },
+        }
+    }
+
+    fn resources(inputs: DispatchInputs<MockTexture>) -> DispatchResources {
+        inputs.into_resources(to_resource).unwrap()
+    }
+
+    fn parameters(exposure: Fsr2ExposureMode) -> Fsr2RenderParametersSnapshot {
+        Fsr2RenderParametersSnapshot {
+            motion_vector_scale: None,
+            exposure,
+            input_resolution: INPUT_RESOLUTION,
+            sharpness: 0.0,
+            frame_delta_time: Duration::from_millis(16),
+            reset: false,
+            camera_near: 0.1,
+            camera_far: Some(100.0),
+            camera_fov_angle_vertical: 1.0,
+            camera_view: None,
+            jitter_offset: Vec2::ZERO,
+        }
+    }
+
+    fn registered(backend: &MockBackend, texture: MockTexture) -> Option<&FfxResource> {
+        backend
+            .registered_resources
+            .iter()
+            .find(|resource| resource.resource as usize == texture.address)
+    }
+
+    fn constant_buffers_contain(backend: &MockBackend, value: f32) -> bool {
+        backend
+            .scheduled_jobs
+            .iter()
+            .filter(|job| job.jobType == FfxGpuJobType_FFX_GPU_JOB_COMPUTE)
+            .flat_map(|job| unsafe { job.__bindgen_anon_1.computeJobDescriptor.cbs })
+            .any(|cb| cb.data[..cb.uint32Size as usize].contains(&value.to_bits()))
+    }
+
+    #[test]
+    fn initialization_flags_reach_pipelines() {
+        let context = context(
+            Fsr2InitializationFlags::AUTO_EXPOSURE
+                | Fsr2InitializationFlags::INVERTED_DEPTH
+                | Fsr2InitializationFlags::HIGH_DYNAMIC_RANGE,
+        );
+
+        let expected = FfxFsr2InitializationFlagBits_FFX_FSR2_ENABLE_AUTO_EXPOSURE
+            | FfxFsr2InitializationFlagBits_FFX_FSR2_ENABLE_DEPTH_INVERTED
+            | FfxFsr2InitializationFlagBits_FFX_FSR2_ENABLE_HIGH_DYNAMIC_RANGE;
+        let pipeline_context_flags = &context.backend().pipeline_context_flags;
+        assert!(!pipeline_context_flags.is_empty());
+        assert!(pipeline_context_flags
+            .iter()
+            .all(|&flags| flags == expected as u32));
+    }
+
+    #[test]
+    fn manual_exposure_registers_exposure_resource() {
+        let mut context = context(Fsr2InitializationFlags::empty());
+        context
+            .dispatch(
+                resources(inputs(Some(EXPOSURE), None)),
+                &parameters(Fsr2ExposureMode::ManualExposure { pre_exposure: 1.0 }),
+            )
+            .unwrap();
+
+        let exposure = registered(context.backend(), EXPOSURE).unwrap();
+        assert_eq!(
+            exposure.state,
+            FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ
+        );
+    }
+
+    #[test]
+    fn pre_exposure_reaches_dispatch() {
+        let pre_exposure = 1.2345;
+        let parameters = parameters(Fsr2ExposureMode::ManualExposure { pre_exposure });
+
+        let description = dispatch_description(
+            ptr::null_mut(),
+            resources(inputs(Some(EXPOSURE), None)),
+            &parameters,
+        );
+        assert_eq!(description.preExposure, pre_exposure);
+
+        let mut context = context(Fsr2InitializationFlags::empty());
+        context
+            .dispatch(resources(inputs(Some(EXPOSURE), None)), &parameters)
+            .unwrap();
+        assert!(constant_buffers_contain(context.backend(), pre_exposure));
+    }
+
+    #[test]
+    fn auto_exposure_uses_unit_pre_exposure_and_no_exposure_resource() {
+        let mut context = context(Fsr2InitializationFlags::AUTO_EXPOSURE);
+        let parameters = parameters(Fsr2ExposureMode::AutoExposure);
+
+        let description =
+            dispatch_description(ptr::null_mut(), resources(inputs(None, None)), &parameters);
+        assert_eq!(description.preExposure, 1.0);
+        assert!(description.exposure.resource.is_null());
+
+        context
+            .dispatch(resources(inputs(None, None)), &parameters)
+            .unwrap();
+        assert!(registered(context.backend(), EXPOSURE).is_none());
+    }
+
+    #[test]
+    fn no_reactive_mask_is_a_null_resource() {
+        let parameters = parameters(Fsr2ExposureMode::AutoExposure);
+
+        let description =
+            dispatch_description(ptr::null_mut(), resources(inputs(None, None)), &parameters);
+        assert!(description.reactive.resource.is_null());
+        assert!(description.transparencyAndComposition.resource.is_null());
+
+        let mut context = context(Fsr2InitializationFlags::AUTO_EXPOSURE);
+        context
+            .dispatch(resources(inputs(None, None)), &parameters)
+            .unwrap();
+        assert!(registered(context.backend(), REACTIVE_MASK).is_none());
+    }
+
+    #[test]
+    fn manual_reactive_mask_is_registered_for_generic_read() {
+        let mut context = context(Fsr2InitializationFlags::AUTO_EXPOSURE);
+        context
+            .dispatch(
+                resources(inputs(None, Some(REACTIVE_MASK))),
+                &parameters(Fsr2ExposureMode::AutoExposure),
+            )
+            .unwrap();
+
+        let reactive_mask = registered(context.backend(), REACTIVE_MASK).unwrap();
+        assert_eq!(
+            reactive_mask.state,
+            FfxResourceStates_FFX_RESOURCE_STATE_GENERIC_READ
+        );
+    }
+
+    #[test]
+    fn output_is_registered_for_unordered_access() {
+        let description = dispatch_description(
+            ptr::null_mut(),
+            resources(inputs(None, None)),
+            &parameters(Fsr2ExposureMode::AutoExposure),
+        );
+        assert_eq!(
+            description.output.state,
+            FfxResourceStates_FFX_RESOURCE_STATE_UNORDERED_ACCESS
+        );
+        assert_eq!(
+            description.color.state,
+            FfxResourceStates_FFX_RESOURCE_STATE_COMPUTE_READ
+        );
+    }
+
+    #[test]
+    fn sharpness_is_clamped() {
+        let mut parameters = parameters(Fsr2ExposureMode::AutoExposure);
+
+        parameters.sharpness = 2.0;
+        let description =
+            dispatch_description(ptr::null_mut(), resources(inputs(None, None)), &parameters);
+        assert!(description.enableSharpening);
+        assert_eq!(description.sharpness, 1.0);
+
+        parameters.sharpness = 0.0;
+        let description =
+            dispatch_description(ptr::null_mut(), resources(inputs(None, None)), &parameters);
+        assert!(!description.enableSharpening);
+    }
+
+    #[test]
+    fn frame_time_delta_keeps_sub_millisecond_precision() {
+        let mut parameters = parameters(Fsr2ExposureMode::AutoExposure);
+        parameters.frame_delta_time = Duration::from_secs_f32(1.0 / 240.0);
+
+        let description =
+            dispatch_description(ptr::null_mut(), resources(inputs(None, None)), &parameters);
+        assert!((description.frameTimeDelta - 4.1667).abs() < 1e-3);
+    }
+
+    #[test]
+    fn long_pause_is_clamped_to_max_frame_delta_time() {
+        let mut parameters = parameters(Fsr2ExposureMode::AutoExposure);
+        parameters.frame_delta_time = frame_delta_time(None, Duration::from_secs(30));
+
+        let description =
+            dispatch_description(ptr::null_mut(), resources(inputs(None, None)), &parameters);
+        assert_eq!(description.frameTimeDelta, 100.0);
+
+        let mut context = context(Fsr2InitializationFlags::AUTO_EXPOSURE);
+        context
+            .dispatch(resources(inputs(None, None)), &parameters)
+            .unwrap();
+    }
+
+    #[test]
+    fn dispatch_executes_one_batch_of_jobs() {
+        let mut context = context(Fsr2InitializationFlags::empty());
+        assert!(!context.backend().created_resources.is_empty());
+
+        context
+            .dispatch(
+                resources(inputs(None, None)),
+                &parameters(Fsr2ExposureMode::AutoExposure),
+            )
+            .unwrap();
+
+        assert_eq!(context.backend().executed_job_batches, 1);
+        assert!(!context.backend().scheduled_jobs.is_empty());
+    }
+}
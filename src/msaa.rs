@@ -0,0 +1,420 @@
+use crate::Fsr2Texture;
+use glam::UVec2;
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, CommandEncoder, ComputePassDescriptor,
+    ComputePipeline, ComputePipelineDescriptor, Device, Extent3d, PipelineLayoutDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, StorageTextureAccess, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+const RESOLVED_COLOR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+const RESOLVED_DEPTH_FORMAT: TextureFormat = TextureFormat::R32Float;
+
+/// Resolves multisampled color and depth inputs into single sampled textures FSR can consume.
+///
+/// Color is resolved with a tonemapped average, and depth by picking the sample closest to the camera.
+pub(crate) struct MsaaResolver {
+    color_bind_group_layout: BindGroupLayout,
+    color_pipeline: ComputePipeline,
+    depth_bind_group_layout: BindGroupLayout,
+    depth_pipeline: ComputePipeline,
+    resolved_color: Texture,
+    resolved_color_view: TextureView,
+    resolved_depth: Texture,
+    resolved_depth_view: TextureView,
+}
+
+impl MsaaResolver {
+    pub fn new(device: &Device, max_input_resolution: UVec2, inverted_depth: bool) -> Self {
+        let color_bind_group_layout = resolve_bind_group_layout(
+            device,
+            "fsr2_msaa_resolve_color_bind_group_layout",
+            TextureSampleType::Float { filterable: false },
+            RESOLVED_COLOR_FORMAT,
+        );
+        let color_pipeline = resolve_pipeline(
+            device,
+            "fsr2_msaa_resolve_color_pipeline",
+            &color_bind_group_layout,
+            ShaderSource::Wgsl(include_str!("shaders/msaa_resolve_color.wgsl").into()),
+            "resolve_color",
+        );
+
+        let depth_bind_group_layout = resolve_bind_group_layout(
+            device,
+            "fsr2_msaa_resolve_depth_bind_group_layout",
+            TextureSampleType::Depth,
+            RESOLVED_DEPTH_FORMAT,
+        );
+        let depth_pipeline = resolve_pipeline(
+            device,
+            "fsr2_msaa_resolve_depth_pipeline",
+            &depth_bind_group_layout,
+            ShaderSource::Wgsl(include_str!("shaders/msaa_resolve_depth.wgsl").into()),
+            if inverted_depth {
+                "resolve_depth_inverted"
+            } else {
+                "resolve_depth"
+            },
+        );
+
+        let resolved_color = resolve_target(
+            device,
+            "fsr2_msaa_resolved_color",
+            max_input_resolution,
+            RESOLVED_COLOR_FORMAT,
+        );
+        let resolved_depth = resolve_target(
+            device,
+            "fsr2_msaa_resolved_depth",
+            max_input_resolution,
+            RESOLVED_DEPTH_FORMAT,
+        );
+
+        Self {
+            color_bind_group_layout,
+            color_pipeline,
+            depth_bind_group_layout,
+            depth_pipeline,
+            resolved_color_view: resolved_color.create_view(&TextureViewDescriptor::default()),
+            resolved_color,
+            resolved_depth_view: resolved_depth.create_view(&TextureViewDescriptor::default()),
+            resolved_depth,
+        }
+    }
+
+    pub fn resolve_color(
+        &self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        color: &Fsr2Texture,
+        input_resolution: UVec2,
+    ) -> Fsr2Texture<'_> {
+        resolve(
+            device,
+            command_encoder,
+            &self.color_pipeline,
+            &self.color_bind_group_layout,
+            color.view,
+            &self.resolved_color_view,
+            input_resolution,
+        );
+
+        Fsr2Texture {
+            texture: &self.resolved_color,
+            view: &self.resolved_color_view,
+        }
+    }
+
+    pub fn resolve_depth(
+        &self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        depth: &Fsr2Texture,
+        input_resolution: UVec2,
+    ) -> Fsr2Texture<'_> {
+        // Textures with a stencil aspect can only be bound through a view of their depth aspect
+        let depth_view = depth.texture.create_view(&TextureViewDescriptor {
+            aspect: TextureAspect::DepthOnly,
+            ..Default::default()
+        });
+        resolve(
+            device,
+            command_encoder,
+            &self.depth_pipeline,
+            &self.depth_bind_group_layout,
+            &depth_view,
+            &self.resolved_depth_view,
+            input_resolution,
+        );
+
+        Fsr2Texture {
+            texture: &self.resolved_depth,
+            view: &self.resolved_depth_view,
+        }
+    }
+}
+
+fn resolve(
+    device: &Device,
+    command_encoder: &mut CommandEncoder,
+    pipeline: &ComputePipeline,
+    bind_group_layout: &BindGroupLayout,
+    input: &TextureView,
+    output: &TextureView,
+    input_resolution: UVec2,
+) {
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("fsr2_msaa_resolve_bind_group"),
+        layout: bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(input),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(output),
+            },
+        ],
+    });
+
+    let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+        label: Some("fsr2_msaa_resolve"),
+    });
+    pass.set_pipeline(pipeline);
+    pass.set_bind_group(0, &bind_group, &[]);
+    let workgroups = (input_resolution + 7) / 8;
+    pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+}
+
+fn resolve_bind_group_layout(
+    device: &Device,
+    label: &str,
+    input_sample_type: TextureSampleType,
+    output_format: TextureFormat,
+) -> BindGroupLayout {
+    device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Texture {
+                    sample_type: input_sample_type,
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: true,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::StorageTexture {
+                    access: StorageTextureAccess::WriteOnly,
+                    format: output_format,
+                    view_dimension: TextureViewDimension::D2,
+                },
+                count: None,
+            },
+        ],
+    })
+}
+
+fn resolve_pipeline(
+    device: &Device,
+    label: &str,
+    bind_group_layout: &BindGroupLayout,
+    source: ShaderSource,
+    entry_point: &str,
+) -> ComputePipeline {
+    let module = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some(label),
+        source,
+    });
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        module: &module,
+        entry_point,
+    })
+}
+
+fn resolve_target(device: &Device, label: &str, size: UVec2, format: TextureFormat) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some(label),
+        size: Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroU32;
+    use std::sync::mpsc;
+    use wgpu::{
+        BufferDescriptor, BufferUsages, Color, CommandEncoderDescriptor, DeviceDescriptor,
+        ImageCopyBuffer, ImageDataLayout, Instance, LoadOp, Maintain, MapMode, Operations, Queue,
+        RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+        COPY_BYTES_PER_ROW_ALIGNMENT,
+    };
+
+    const SIZE: UVec2 = UVec2::new(8, 8);
+
+    fn device() -> Option<(Device, Queue)> {
+        let instance = Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+        pollster::block_on(adapter.request_device(&DeviceDescriptor::default(), None)).ok()
+    }
+
+    fn multisampled_texture(device: &Device, format: TextureFormat) -> (Texture, TextureView) {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: SIZE.x,
+                height: SIZE.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 4,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    /// Submit `command_encoder`, and read back the first texel of `resolved`.
+    fn read_first_texel(
+        device: &Device,
+        queue: &Queue,
+        mut command_encoder: CommandEncoder,
+        resolved: &Texture,
+        bytes_per_texel: usize,
+    ) -> Vec<u8> {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: COPY_BYTES_PER_ROW_ALIGNMENT as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        command_encoder.copy_texture_to_buffer(
+            resolved.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit([command_encoder.finish()]);
+
+        let (sender, receiver) = mpsc::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(MapMode::Read, move |result| sender.send(result).unwrap());
+        device.poll(Maintain::Wait);
+        receiver.recv().unwrap().unwrap();
+        let texel = slice.get_mapped_range()[..bytes_per_texel].to_vec();
+        texel
+    }
+
+    #[test]
+    fn color_is_resolved() {
+        let Some((device, queue)) = device() else {
+            return;
+        };
+        let resolver = MsaaResolver::new(&device, SIZE, false);
+        let (texture, view) = multisampled_texture(&device, TextureFormat::Rgba16Float);
+
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+        command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color {
+                        r: 0.5,
+                        g: 1.0,
+                        b: 2.0,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        let color = Fsr2Texture {
+            texture: &texture,
+            view: &view,
+        };
+        let resolved = resolver.resolve_color(&device, &mut command_encoder, &color, SIZE);
+        let texel = read_first_texel(&device, &queue, command_encoder, resolved.texture, 8);
+
+        // Every sample is the same, so the tonemapped average gives it back, as half floats
+        let channels: Vec<u16> = texel
+            .chunks(2)
+            .map(|channel| u16::from_ne_bytes([channel[0], channel[1]]))
+            .collect();
+        for (channel, expected) in channels
+            .into_iter()
+            .zip([0x3800u16, 0x3c00, 0x4000, 0x3c00])
+        {
+            assert!(
+                channel.abs_diff(expected) <= 1,
+                "{channel:#x} != {expected:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn depth_is_resolved_from_depth_and_depth_stencil_formats() {
+        let Some((device, queue)) = device() else {
+            return;
+        };
+
+        for format in [
+            TextureFormat::Depth32Float,
+            TextureFormat::Depth24PlusStencil8,
+        ] {
+            let resolver = MsaaResolver::new(&device, SIZE, false);
+            let (texture, view) = multisampled_texture(&device, format);
+
+            let mut command_encoder =
+                device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+            command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(0.25),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            let depth = Fsr2Texture {
+                texture: &texture,
+                view: &view,
+            };
+            let resolved = resolver.resolve_depth(&device, &mut command_encoder, &depth, SIZE);
+            let texel = read_first_texel(&device, &queue, command_encoder, resolved.texture, 4);
+
+            let depth = f32::from_ne_bytes(texel.try_into().unwrap());
+            assert!((depth - 0.25).abs() < 1e-6, "{format:?}: {depth}");
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::async_compute::Transfer;
+use crate::{
+    Fsr2Context, Fsr2ContextDescriptor, Fsr2Error, Fsr2RenderParameters, Fsr2Texture, Fsr2WgpuError,
+};
+use ash::vk;
+use std::ops::Deref;
+use wgpu::{Adapter, CommandEncoder, Device};
+
+/// Upscales several views rendered into layers of the same array textures, e.g. the two eyes of a
+/// VR headset.
+///
+/// Every view has its own [`Fsr2Context`], and so its own history, jitter sequence and camera.
+/// The textures given for view `i` must either be views of layer `i` of an array texture
+/// (with [`wgpu::TextureViewDimension::D2`], `base_array_layer: i` and `array_layer_count: 1`), or
+/// 2D textures of their own.
+pub struct Fsr2MultiviewContext<D: Deref<Target = Device> + Clone> {
+    views: Vec<Fsr2Context<D>>,
+}
+
+impl<D: Deref<Target = Device> + Clone> Fsr2MultiviewContext<D> {
+    pub fn new(
+        device: D,
+        adapter: &Adapter,
+        descriptor: Fsr2ContextDescriptor,
+        view_count: u32,
+    ) -> Result<Self, Fsr2Error> {
+        let views = (0..view_count)
+            .map(|_| Fsr2Context::new(device.clone(), adapter, descriptor.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { views })
+    }
+
+    pub fn view_count(&self) -> u32 {
+        self.views.len() as u32
+    }
+
+    /// The context upscaling view `index`, e.g. for its jitter offset.
+    pub fn view(&self, index: u32) -> &Fsr2Context<D> {
+        &self.views[index as usize]
+    }
+
+    pub fn view_mut(&mut self, index: u32) -> &mut Fsr2Context<D> {
+        &mut self.views[index as usize]
+    }
+
+    /// Upscale every view, given one set of parameters per view, in view order.
+    ///
+    /// Returns the managed output texture written to for each view whose
+    /// [`Fsr2RenderParameters::output`] was `None`.
+    pub fn render(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        parameters: &[Fsr2RenderParameters],
+    ) -> Result<Vec<Option<Fsr2Texture<'_>>>, Fsr2WgpuError> {
+        if parameters.len() != self.views.len() {
+            return Err(Fsr2WgpuError::ViewCountMismatch {
+                expected: self.views.len(),
+                actual: parameters.len(),
+            });
+        }
+
+        self.views
+            .iter_mut()
+            .zip(parameters)
+            .enumerate()
+            .map(|(layer, (view, parameters))| {
+                view.render_layer(command_encoder, *parameters, layer as u32)
+            })
+            .collect()
+    }
+
+    /// [`Fsr2Context::render_on_compute`] for every view, given one set of parameters per view,
+    /// in view order.
+    ///
+    /// # Safety
+    /// See [`Fsr2Context::render_on_compute`].
+    pub unsafe fn render_on_compute(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        parameters: &[Fsr2RenderParameters],
+    ) -> Result<Vec<Option<Fsr2Texture<'_>>>, Fsr2WgpuError> {
+        if parameters.len() != self.views.len() {
+            return Err(Fsr2WgpuError::ViewCountMismatch {
+                expected: self.views.len(),
+                actual: parameters.len(),
+            });
+        }
+
+        self.views
+            .iter_mut()
+            .zip(parameters)
+            .enumerate()
+            .map(|(layer, (view, parameters))| {
+                view.render_layer_on_compute(command_buffer, *parameters, layer as u32)
+            })
+            .collect()
+    }
+
+    /// [`Fsr2Context::release_to_compute`] for every view, given the parameters of each.
+    pub fn release_to_compute(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        parameters: &[Fsr2RenderParameters],
+    ) {
+        for (layer, (view, parameters)) in self.views.iter().zip(parameters).enumerate() {
+            view.transfer_textures(command_encoder, parameters, layer as u32, Transfer::Release);
+        }
+    }
+
+    /// [`Fsr2Context::acquire_from_compute`] for every view, given the parameters of each.
+    pub fn acquire_from_compute(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        parameters: &[Fsr2RenderParameters],
+    ) {
+        for (layer, (view, parameters)) in self.views.iter().zip(parameters).enumerate() {
+            view.transfer_textures(command_encoder, parameters, layer as u32, Transfer::Acquire);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use crate::Fsr2Texture;
+use glam::UVec2;
+use wgpu::{
+    Device, Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureView, TextureViewDescriptor,
+};
+
+/// Output textures owned by the context, alternated between each frame so that the previous
+/// frame's output can still be read while the current one is being written.
+pub(crate) struct ManagedOutput {
+    textures: [(Texture, TextureView); 2],
+    current: usize,
+}
+
+impl ManagedOutput {
+    /// `encoded` outputs are rendered to when encoding to the descriptor's color space.
+    pub fn new(
+        device: &Device,
+        upscaled_resolution: UVec2,
+        format: TextureFormat,
+        encoded: bool,
+    ) -> Self {
+        let mut usage = TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_SRC;
+        if encoded {
+            usage |= TextureUsages::RENDER_ATTACHMENT;
+        }
+        let create_texture = || {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: Some("fsr2_output"),
+                size: Extent3d {
+                    width: upscaled_resolution.x,
+                    height: upscaled_resolution.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            (texture, view)
+        };
+
+        Self {
+            textures: [create_texture(), create_texture()],
+            current: 0,
+        }
+    }
+
+    pub fn advance(&mut self) {
+        self.current = (self.current + 1) % self.textures.len();
+    }
+
+    /// The texture [`ManagedOutput::advance`] moves on to, for the frame being rendered.
+    pub fn next(&self) -> Fsr2Texture<'_> {
+        let (texture, view) = &self.textures[(self.current + 1) % self.textures.len()];
+        Fsr2Texture { texture, view }
+    }
+
+    pub fn current(&self) -> Fsr2Texture<'_> {
+        let (texture, view) = &self.textures[self.current];
+        Fsr2Texture { texture, view }
+    }
+}
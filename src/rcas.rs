@@ -0,0 +1,186 @@
+use crate::{Fsr2Texture, Fsr2WgpuError};
+use std::collections::HashMap;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferUsages,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    PipelineLayoutDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+    StorageTextureAccess, TextureFormat, TextureSampleType, TextureViewDimension,
+};
+
+/// FSR's RCAS sharpening as a standalone pass, for sharpening images that weren't upscaled by FSR.
+///
+/// The output texture must have [`wgpu::TextureUsages::STORAGE_BINDING`] and be one of
+/// `Rgba8Unorm`, `Rgba16Float` or `Rgba32Float`. The input is sampled with clamped coordinates, so
+/// it may be larger than the output but should not be smaller.
+pub struct RcasPass {
+    pipelines: HashMap<TextureFormat, (BindGroupLayout, ComputePipeline)>,
+}
+
+impl RcasPass {
+    pub fn new() -> Self {
+        Self {
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Sharpen `input` into `output`. `sharpness` has the same range and effect as
+    /// [`Fsr2RenderParameters::sharpness`](crate::Fsr2RenderParameters::sharpness).
+    pub fn record(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        input: Fsr2Texture,
+        output: Fsr2Texture,
+        sharpness: f32,
+    ) -> Result<(), Fsr2WgpuError> {
+        let output_format = output.texture.format();
+        if !self.pipelines.contains_key(&output_format) {
+            let pipeline = rcas_pipeline(device, output_format)?;
+            self.pipelines.insert(output_format, pipeline);
+        }
+        let (bind_group_layout, pipeline) = &self.pipelines[&output_format];
+
+        let mut parameters = Vec::with_capacity(16);
+        parameters.extend_from_slice(&sharpness_scale(sharpness).to_ne_bytes());
+        parameters.extend_from_slice(&[0; 12]);
+        let parameters = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fsr2_rcas_parameters"),
+            contents: &parameters,
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fsr2_rcas_bind_group"),
+            layout: bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(input.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(output.view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: parameters.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("fsr2_rcas"),
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(
+            output.texture.width().div_ceil(8),
+            output.texture.height().div_ceil(8),
+            1,
+        );
+
+        Ok(())
+    }
+}
+
+impl Default for RcasPass {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Map sharpness to RCAS's lobe scale the way FSR2 does: sharpness is remapped to `2 - 2 *
+/// sharpness` stops of attenuation, and a sharpness of zero disables sharpening entirely.
+fn sharpness_scale(sharpness: f32) -> f32 {
+    if sharpness > 0.0 {
+        let attenuation = 2.0 - 2.0 * sharpness.clamp(0.0, 1.0);
+        (-attenuation).exp2()
+    } else {
+        0.0
+    }
+}
+
+fn rcas_pipeline(
+    device: &Device,
+    output_format: TextureFormat,
+) -> Result<(BindGroupLayout, ComputePipeline), Fsr2WgpuError> {
+    let wgsl_format = match output_format {
+        TextureFormat::Rgba8Unorm => "rgba8unorm",
+        TextureFormat::Rgba16Float => "rgba16float",
+        TextureFormat::Rgba32Float => "rgba32float",
+        _ => return Err(Fsr2WgpuError::UnsupportedFormat(output_format)),
+    };
+
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("fsr2_rcas_bind_group_layout"),
+        entries: &[
+            BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::StorageTexture {
+                    access: StorageTextureAccess::WriteOnly,
+                    format: output_format,
+                    view_dimension: TextureViewDimension::D2,
+                },
+                count: None,
+            },
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    let module = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("fsr2_rcas_shader"),
+        source: ShaderSource::Wgsl(
+            include_str!("shaders/rcas.wgsl")
+                .replace("OUTPUT_FORMAT", wgsl_format)
+                .into(),
+        ),
+    });
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("fsr2_rcas_pipeline_layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("fsr2_rcas_pipeline"),
+        layout: Some(&layout),
+        module: &module,
+        entry_point: "rcas",
+    });
+
+    Ok((bind_group_layout, pipeline))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sharpness_scale_matches_fsr2() {
+        assert_eq!(sharpness_scale(0.0), 0.0);
+        assert_eq!(sharpness_scale(0.5), 0.5);
+        assert_eq!(sharpness_scale(1.0), 1.0);
+        assert_eq!(sharpness_scale(2.0), 1.0);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::Fsr2RenderParametersSnapshot;
+use glam::{Mat4, UVec2};
+use std::time::Duration;
+
+/// Limits on how much can change between two frames before the context resets FSR's history on
+/// its own, see [`Fsr2ContextDescriptor::reset_detection`](crate::Fsr2ContextDescriptor).
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fsr2ResetThresholds {
+    /// Distance in world units the camera may move in one frame.
+    pub max_camera_translation: f32,
+    /// Angle in radians the camera may turn in one frame.
+    pub max_camera_rotation: f32,
+    /// Change in radians of the vertical field of view allowed in one frame.
+    pub max_fov_change: f32,
+    /// Time between two frames after which history is considered stale.
+    pub max_frame_gap: Duration,
+}
+
+impl Default for Fsr2ResetThresholds {
+    fn default() -> Self {
+        Self {
+            max_camera_translation: 2.0,
+            max_camera_rotation: 45.0f32.to_radians(),
+            max_fov_change: 10.0f32.to_radians(),
+            max_frame_gap: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct FrameState {
+    camera_view: Option<Mat4>,
+    camera_fov_angle_vertical: f32,
+    input_resolution: UVec2,
+}
+
+/// Decides whether a frame is discontinuous with the previous one, e.g. after a camera cut.
+#[derive(Clone)]
+pub(crate) struct ResetDetector {
+    thresholds: Fsr2ResetThresholds,
+    // With dynamic resolution FSR expects the input resolution to change between frames
+    dynamic_resolution: bool,
+    previous: Option<FrameState>,
+}
+
+impl ResetDetector {
+    pub fn new(thresholds: Fsr2ResetThresholds, dynamic_resolution: bool) -> Self {
+        Self {
+            thresholds,
+            dynamic_resolution,
+            previous: None,
+        }
+    }
+
+    /// Compare a frame against the previous one. `elapsed` is the time since the previous frame,
+    /// before any clamping.
+    pub fn should_reset(
+        &mut self,
+        parameters: &Fsr2RenderParametersSnapshot,
+        elapsed: Duration,
+    ) -> bool {
+        let current = FrameState {
+            camera_view: parameters.camera_view,
+            camera_fov_angle_vertical: parameters.camera_fov_angle_vertical,
+            input_resolution: parameters.input_resolution,
+        };
+        let reset = match &self.previous {
+            Some(previous) => self.is_discontinuous(previous, &current, elapsed),
+            None => false,
+        };
+        self.previous = Some(current);
+
+        reset
+    }
+
+    fn is_discontinuous(
+        &self,
+        previous: &FrameState,
+        current: &FrameState,
+        elapsed: Duration,
+    ) -> bool {
+        let camera_cut = match (previous.camera_view, current.camera_view) {
+            (Some(previous_view), Some(current_view)) => {
+                let (_, previous_rotation, previous_translation) =
+                    previous_view.inverse().to_scale_rotation_translation();
+                let (_, current_rotation, current_translation) =
+                    current_view.inverse().to_scale_rotation_translation();
+
+                previous_translation.distance(current_translation)
+                    > self.thresholds.max_camera_translation
+                    || previous_rotation.angle_between(current_rotation)
+                        > self.thresholds.max_camera_rotation
+            }
+            _ => false,
+        };
+        let fov_change = (current.camera_fov_angle_vertical - previous.camera_fov_angle_vertical)
+            .abs()
+            > self.thresholds.max_fov_change;
+        let resolution_change =
+            !self.dynamic_resolution && current.input_resolution != previous.input_resolution;
+        let frame_gap = elapsed > self.thresholds.max_frame_gap;
+
+        camera_cut || fov_change || resolution_change || frame_gap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Fsr2ExposureMode;
+    use glam::{Vec2, Vec3};
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    fn parameters(camera_position: Vec3, camera_yaw: f32) -> Fsr2RenderParametersSnapshot {
+        let camera_transform =
+            Mat4::from_translation(camera_position) * Mat4::from_rotation_y(camera_yaw);
+
+        Fsr2RenderParametersSnapshot {
+            motion_vector_scale: None,
+            exposure: Fsr2ExposureMode::AutoExposure,
+            input_resolution: UVec2::new(1280, 720),
+            sharpness: 0.0,
+            frame_delta_time: FRAME,
+            reset: false,
+            camera_near: 0.1,
+            camera_far: None,
+            camera_fov_angle_vertical: 60.0f32.to_radians(),
+            camera_view: Some(camera_transform.inverse()),
+            jitter_offset: Vec2::ZERO,
+        }
+    }
+
+    fn detector() -> ResetDetector {
+        ResetDetector::new(Fsr2ResetThresholds::default(), false)
+    }
+
+    #[test]
+    fn first_frame_does_not_reset() {
+        assert!(!detector().should_reset(&parameters(Vec3::ZERO, 0.0), FRAME));
+    }
+
+    #[test]
+    fn smooth_camera_motion_does_not_reset() {
+        let mut detector = detector();
+        for frame in 0..10 {
+            let position = Vec3::new(frame as f32 * 0.1, 0.0, 0.0);
+            let yaw = (frame as f32).to_radians();
+            assert!(!detector.should_reset(&parameters(position, yaw), FRAME));
+        }
+    }
+
+    #[test]
+    fn camera_teleport_resets() {
+        let mut detector = detector();
+        detector.should_reset(&parameters(Vec3::ZERO, 0.0), FRAME);
+        assert!(detector.should_reset(&parameters(Vec3::new(0.0, 0.0, 50.0), 0.0), FRAME));
+    }
+
+    #[test]
+    fn camera_turning_around_resets() {
+        let mut detector = detector();
+        detector.should_reset(&parameters(Vec3::ZERO, 0.0), FRAME);
+        assert!(detector.should_reset(&parameters(Vec3::ZERO, 180.0f32.to_radians()), FRAME));
+    }
+
+    #[test]
+    fn fov_change_resets() {
+        let mut detector = detector();
+        detector.should_reset(&parameters(Vec3::ZERO, 0.0), FRAME);
+
+        let mut zoomed = parameters(Vec3::ZERO, 0.0);
+        zoomed.camera_fov_angle_vertical = 20.0f32.to_radians();
+        assert!(detector.should_reset(&zoomed, FRAME));
+    }
+
+    #[test]
+    fn input_resolution_change_resets_without_dynamic_resolution() {
+        let mut resized = parameters(Vec3::ZERO, 0.0);
+        resized.input_resolution = UVec2::new(960, 540);
+
+        let mut detector = detector();
+        detector.should_reset(&parameters(Vec3::ZERO, 0.0), FRAME);
+        assert!(detector.should_reset(&resized, FRAME));
+
+        let mut detector = ResetDetector::new(Fsr2ResetThresholds::default(), true);
+        detector.should_reset(&parameters(Vec3::ZERO, 0.0), FRAME);
+        assert!(!detector.should_reset(&resized, FRAME));
+    }
+
+    #[test]
+    fn frame_gap_resets() {
+        let mut detector = detector();
+        detector.should_reset(&parameters(Vec3::ZERO, 0.0), FRAME);
+        assert!(detector.should_reset(&parameters(Vec3::ZERO, 0.0), Duration::from_secs(5)));
+    }
+}
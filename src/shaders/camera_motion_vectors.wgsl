@@ -0,0 +1,31 @@
+// Motion vectors from camera movement alone, by reprojecting depth into the previous frame
+
+struct CameraMotionParameters {
+    current_inverse_view_projection: mat4x4<f32>,
+    previous_view_projection: mat4x4<f32>,
+    input_size: vec2<f32>,
+}
+
+@group(0) @binding(0) var depth: texture_depth_2d;
+@group(0) @binding(1) var motion_vectors: texture_storage_2d<rg16float, write>;
+@group(0) @binding(2) var<uniform> parameters: CameraMotionParameters;
+
+@compute @workgroup_size(8, 8, 1)
+fn camera_motion_vectors(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (any(vec2<f32>(id.xy) >= parameters.input_size)) {
+        return;
+    }
+    let coords = vec2<i32>(id.xy);
+
+    let uv = (vec2<f32>(id.xy) + 0.5) / parameters.input_size;
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    // Points at infinite depth end up with w = 0, and so only rotate with the camera
+    let world = parameters.current_inverse_view_projection
+        * vec4<f32>(ndc, textureLoad(depth, coords, 0), 1.0);
+
+    let previous_clip = parameters.previous_view_projection * world;
+    let previous_ndc = previous_clip.xy / previous_clip.w;
+    let previous_uv = vec2<f32>(previous_ndc.x * 0.5 + 0.5, 0.5 - previous_ndc.y * 0.5);
+
+    textureStore(motion_vectors, coords, vec4<f32>(previous_uv - uv, 0.0, 0.0));
+}
@@ -0,0 +1,97 @@
+// Converts between the linear color FSR works with and encoded color spaces
+// Transfer 0: linear, 1: sRGB, 2: PQ, with 1.0 in linear mapped to 80 nits as in scRGB
+
+struct ColorSpaceParameters {
+    transfer: u32,
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> parameters: ColorSpaceParameters;
+
+@vertex
+fn vertex(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+fn load(position: vec4<f32>) -> vec4<f32> {
+    let max_coords = vec2<i32>(textureDimensions(source)) - 1;
+    return textureLoad(source, clamp(vec2<i32>(position.xy), vec2<i32>(0), max_coords), 0);
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    return select(
+        pow((color + 0.055) / 1.055, vec3<f32>(2.4)),
+        color / 12.92,
+        color <= vec3<f32>(0.04045)
+    );
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let clamped = max(color, vec3<f32>(0.0));
+    return select(
+        1.055 * pow(clamped, vec3<f32>(1.0 / 2.4)) - 0.055,
+        clamped * 12.92,
+        clamped <= vec3<f32>(0.0031308)
+    );
+}
+
+// SMPTE ST 2084 constants
+struct Pq {
+    m1: f32,
+    m2: f32,
+    c1: f32,
+    c2: f32,
+    c3: f32,
+    // 10000 nits, the top of the PQ range, over the 80 nits of linear 1.0
+    scale: f32,
+}
+
+fn pq_constants() -> Pq {
+    return Pq(0.1593017578125, 78.84375, 0.8359375, 18.8515625, 18.6875, 125.0);
+}
+
+fn pq_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let pq = pq_constants();
+    let p = pow(max(color, vec3<f32>(0.0)), vec3<f32>(1.0 / pq.m2));
+    let linear = pow(max(p - pq.c1, vec3<f32>(0.0)) / (pq.c2 - pq.c3 * p), vec3<f32>(1.0 / pq.m1));
+    return linear * pq.scale;
+}
+
+fn linear_to_pq(color: vec3<f32>) -> vec3<f32> {
+    let pq = pq_constants();
+    let p = pow(clamp(color / pq.scale, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(pq.m1));
+    return pow((pq.c1 + pq.c2 * p) / (1.0 + pq.c3 * p), vec3<f32>(pq.m2));
+}
+
+@fragment
+fn decode(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = load(position);
+    switch parameters.transfer {
+        case 1u: {
+            return vec4<f32>(srgb_to_linear(color.rgb), color.a);
+        }
+        case 2u: {
+            return vec4<f32>(pq_to_linear(color.rgb), color.a);
+        }
+        default: {
+            return color;
+        }
+    }
+}
+
+@fragment
+fn encode(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = load(position);
+    switch parameters.transfer {
+        case 1u: {
+            return vec4<f32>(linear_to_srgb(color.rgb), color.a);
+        }
+        case 2u: {
+            return vec4<f32>(linear_to_pq(color.rgb), color.a);
+        }
+        default: {
+            return color;
+        }
+    }
+}
@@ -0,0 +1,18 @@
+struct CropParameters {
+    offset: vec2<u32>,
+    size: vec2<u32>,
+}
+
+@group(0) @binding(0) var input_depth: texture_depth_2d;
+@group(0) @binding(1) var output_depth: texture_storage_2d<r32float, write>;
+@group(0) @binding(2) var<uniform> parameters: CropParameters;
+
+@compute @workgroup_size(8, 8, 1)
+fn crop_depth(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (any(id.xy >= parameters.size)) {
+        return;
+    }
+
+    let depth = textureLoad(input_depth, vec2<i32>(id.xy + parameters.offset), 0);
+    textureStore(output_depth, vec2<i32>(id.xy), vec4<f32>(depth));
+}
@@ -0,0 +1,43 @@
+struct DebugParameters {
+    valid_size: vec2<f32>,
+    mode: u32,
+}
+
+@group(0) @binding(0) var debug_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> parameters: DebugParameters;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return VertexOutput(vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0), uv);
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(min(in.uv * parameters.valid_size, parameters.valid_size - 1.0));
+    let value = textureLoad(debug_texture, coords, 0);
+
+    switch parameters.mode {
+        // Motion vectors
+        case 0u: {
+            return vec4<f32>(saturate(abs(value.rg) * 100.0), 0.0, 1.0);
+        }
+        // Depth
+        case 1u: {
+            return vec4<f32>(vec3<f32>(value.r), 1.0);
+        }
+        // HDR color
+        case 2u: {
+            return vec4<f32>(value.rgb / (max(max(value.r, value.g), value.b) + 1.0), 1.0);
+        }
+        // Everything else
+        default: {
+            return vec4<f32>(saturate(value.rgb), 1.0);
+        }
+    }
+}
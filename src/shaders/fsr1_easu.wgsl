@@ -0,0 +1,187 @@
+// Edge Adaptive Spatial Upsampling, following FsrEasuF from ffx_fsr1.h, with plain loads in place
+// of gathers
+
+struct EasuParameters {
+    // Size of the region of the input texture holding the frame
+    input_size: vec2<f32>,
+}
+
+@group(0) @binding(0) var input_color: texture_2d<f32>;
+@group(0) @binding(1) var output_color: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(2) var<uniform> parameters: EasuParameters;
+
+var<private> base_coords: vec2<i32>;
+
+fn load(offset: vec2<i32>) -> vec3<f32> {
+    let max_coords = vec2<i32>(parameters.input_size) - 1;
+    return textureLoad(input_color, clamp(base_coords + offset, vec2<i32>(0), max_coords), 0).rgb;
+}
+
+// Luma times 2
+fn luma(color: vec3<f32>) -> f32 {
+    return color.b * 0.5 + (color.r * 0.5 + color.g);
+}
+
+struct Direction {
+    direction: vec2<f32>,
+    length: f32,
+}
+
+// Accumulate the edge direction and length of one of the 4 bilinear taps around the sample point
+//    a
+//  b c d
+//    e
+fn accumulate_direction(
+    accumulated: Direction,
+    weight: f32,
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+) -> Direction {
+    var result = accumulated;
+
+    let direction_x = d - b;
+    var length_x = abs(direction_x) / max(max(abs(d - c), abs(c - b)), 1e-5);
+    length_x = clamp(length_x, 0.0, 1.0);
+    result.direction.x += direction_x * weight;
+    result.length += length_x * length_x * weight;
+
+    let direction_y = e - a;
+    var length_y = abs(direction_y) / max(max(abs(e - c), abs(c - a)), 1e-5);
+    length_y = clamp(length_y, 0.0, 1.0);
+    result.direction.y += direction_y * weight;
+    result.length += length_y * length_y * weight;
+
+    return result;
+}
+
+// Shape of the filter kernel for the current pixel
+var<private> kernel_direction: vec2<f32>;
+var<private> kernel_length: vec2<f32>;
+var<private> kernel_lobe: f32;
+var<private> kernel_clip: f32;
+
+var<private> accumulated_color: vec3<f32>;
+var<private> accumulated_weight: f32;
+
+// Accumulate one tap of the anisotropic, approximated Lanczos filter
+fn tap(offset: vec2<f32>, color: vec3<f32>) {
+    // Rotate the offset into the edge's frame, then stretch it
+    let direction = kernel_direction;
+    var v = vec2<f32>(
+        offset.x * direction.x + offset.y * direction.y,
+        offset.x * -direction.y + offset.y * direction.x,
+    );
+    v *= kernel_length;
+
+    let distance2 = min(dot(v, v), kernel_clip);
+    var window = 2.0 / 5.0 * distance2 - 1.0;
+    var base = kernel_lobe * distance2 - 1.0;
+    window *= window;
+    base *= base;
+    window = 25.0 / 16.0 * window - (25.0 / 16.0 - 1.0);
+    let weight = window * base;
+
+    accumulated_color += color * weight;
+    accumulated_weight += weight;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn easu(@builtin(global_invocation_id) id: vec3<u32>) {
+    let output_size = vec2<u32>(textureDimensions(output_color));
+    if (any(id.xy >= output_size)) {
+        return;
+    }
+
+    // Position of the output pixel in input pixel space, with texel centers at integers
+    var position = (vec2<f32>(id.xy) + 0.5) * parameters.input_size / vec2<f32>(output_size) - 0.5;
+    let floored = floor(position);
+    position -= floored;
+    base_coords = vec2<i32>(floored);
+
+    // 12 tap neighborhood, f being the texel at or up and left of the sample point
+    //    b c
+    //  e f g h
+    //  i j k l
+    //    n o
+    let b = load(vec2<i32>(0, -1));
+    let c = load(vec2<i32>(1, -1));
+    let e = load(vec2<i32>(-1, 0));
+    let f = load(vec2<i32>(0, 0));
+    let g = load(vec2<i32>(1, 0));
+    let h = load(vec2<i32>(2, 0));
+    let i = load(vec2<i32>(-1, 1));
+    let j = load(vec2<i32>(0, 1));
+    let k = load(vec2<i32>(1, 1));
+    let l = load(vec2<i32>(2, 1));
+    let n = load(vec2<i32>(0, 2));
+    let o = load(vec2<i32>(1, 2));
+
+    let b_luma = luma(b);
+    let c_luma = luma(c);
+    let e_luma = luma(e);
+    let f_luma = luma(f);
+    let g_luma = luma(g);
+    let h_luma = luma(h);
+    let i_luma = luma(i);
+    let j_luma = luma(j);
+    let k_luma = luma(k);
+    let l_luma = luma(l);
+    let n_luma = luma(n);
+    let o_luma = luma(o);
+
+    // Edge direction and length, bilinearly weighted from the 4 texels around the sample point
+    var edge = Direction(vec2<f32>(0.0), 0.0);
+    let inverse = 1.0 - position;
+    let weights = vec4<f32>(
+        inverse.x * inverse.y,
+        position.x * inverse.y,
+        inverse.x * position.y,
+        position.x * position.y,
+    );
+    edge = accumulate_direction(edge, weights.x, b_luma, e_luma, f_luma, g_luma, j_luma);
+    edge = accumulate_direction(edge, weights.y, c_luma, f_luma, g_luma, h_luma, k_luma);
+    edge = accumulate_direction(edge, weights.z, f_luma, i_luma, j_luma, k_luma, n_luma);
+    edge = accumulate_direction(edge, weights.w, g_luma, j_luma, k_luma, l_luma, o_luma);
+
+    var direction = edge.direction;
+    let direction_length2 = dot(direction, direction);
+    if (direction_length2 < 1.0 / 32768.0) {
+        direction = vec2<f32>(1.0, 0.0);
+    } else {
+        direction *= inverseSqrt(direction_length2);
+    }
+
+    // Shape the filter kernel: stretch it along edges, and sharpen it across them
+    var edge_length = edge.length * 0.5;
+    edge_length *= edge_length;
+    let stretch = dot(direction, direction) / max(abs(direction.x), abs(direction.y));
+    kernel_direction = direction;
+    kernel_length = vec2<f32>(1.0 + (stretch - 1.0) * edge_length, 1.0 - 0.5 * edge_length);
+    kernel_lobe = 0.5 + ((1.0 / 4.0 - 0.04) - 0.5) * edge_length;
+    kernel_clip = 1.0 / kernel_lobe;
+
+    accumulated_color = vec3<f32>(0.0);
+    accumulated_weight = 0.0;
+    tap(vec2<f32>(0.0, -1.0) - position, b);
+    tap(vec2<f32>(1.0, -1.0) - position, c);
+    tap(vec2<f32>(-1.0, 1.0) - position, i);
+    tap(vec2<f32>(0.0, 1.0) - position, j);
+    tap(vec2<f32>(0.0, 0.0) - position, f);
+    tap(vec2<f32>(-1.0, 0.0) - position, e);
+    tap(vec2<f32>(1.0, 1.0) - position, k);
+    tap(vec2<f32>(2.0, 1.0) - position, l);
+    tap(vec2<f32>(2.0, 0.0) - position, h);
+    tap(vec2<f32>(1.0, 0.0) - position, g);
+    tap(vec2<f32>(1.0, 2.0) - position, o);
+    tap(vec2<f32>(0.0, 2.0) - position, n);
+
+    // Clamp to the 4 nearest texels to remove ringing
+    let min4 = min(min(f, g), min(j, k));
+    let max4 = max(max(f, g), max(j, k));
+    let color = clamp(accumulated_color / accumulated_weight, min4, max4);
+
+    textureStore(output_color, vec2<i32>(id.xy), vec4<f32>(color, 1.0));
+}
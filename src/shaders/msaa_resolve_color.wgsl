@@ -0,0 +1,29 @@
+@group(0) @binding(0) var input_color: texture_multisampled_2d<f32>;
+@group(0) @binding(1) var output_color: texture_storage_2d<rgba16float, write>;
+
+// Reversible tonemap, matching FSR2's own, so that bright samples don't dominate the average
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    return color / (max(max(color.r, color.g), color.b) + 1.0);
+}
+
+fn inverse_tonemap(color: vec3<f32>) -> vec3<f32> {
+    return color / max(1.0 - max(max(color.r, color.g), color.b), 1e-5);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn resolve_color(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (any(id.xy >= vec2<u32>(textureDimensions(input_color)))) {
+        return;
+    }
+    let coords = vec2<i32>(id.xy);
+
+    let sample_count = textureNumSamples(input_color);
+    var total = vec4<f32>(0.0);
+    for (var i = 0; i < i32(sample_count); i++) {
+        let sample = textureLoad(input_color, coords, i);
+        total += vec4<f32>(tonemap(sample.rgb), sample.a);
+    }
+    total /= f32(sample_count);
+
+    textureStore(output_color, coords, vec4<f32>(inverse_tonemap(total.rgb), total.a));
+}
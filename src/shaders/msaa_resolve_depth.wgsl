@@ -0,0 +1,32 @@
+@group(0) @binding(0) var input_depth: texture_depth_multisampled_2d;
+@group(0) @binding(1) var output_depth: texture_storage_2d<r32float, write>;
+
+@compute @workgroup_size(8, 8, 1)
+fn resolve_depth(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (any(id.xy >= vec2<u32>(textureDimensions(input_depth)))) {
+        return;
+    }
+    let coords = vec2<i32>(id.xy);
+
+    var closest = 1.0;
+    for (var i = 0; i < i32(textureNumSamples(input_depth)); i++) {
+        closest = min(closest, textureLoad(input_depth, coords, i));
+    }
+
+    textureStore(output_depth, coords, vec4<f32>(closest));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn resolve_depth_inverted(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (any(id.xy >= vec2<u32>(textureDimensions(input_depth)))) {
+        return;
+    }
+    let coords = vec2<i32>(id.xy);
+
+    var closest = 0.0;
+    for (var i = 0; i < i32(textureNumSamples(input_depth)); i++) {
+        closest = max(closest, textureLoad(input_depth, coords, i));
+    }
+
+    textureStore(output_depth, coords, vec4<f32>(closest));
+}
@@ -0,0 +1,64 @@
+// Robust Contrast Adaptive Sharpening, following FsrRcasF from ffx_fsr1.h with noise removal
+// enabled, as FSR2's own sharpening pass uses it
+// The output format is substituted in when the pipeline is created
+
+struct RcasParameters {
+    sharpness_scale: f32,
+}
+
+@group(0) @binding(0) var input_color: texture_2d<f32>;
+@group(0) @binding(1) var output_color: texture_storage_2d<OUTPUT_FORMAT, write>;
+@group(0) @binding(2) var<uniform> parameters: RcasParameters;
+
+fn load(coords: vec2<i32>) -> vec3<f32> {
+    let max_coords = vec2<i32>(textureDimensions(input_color)) - 1;
+    return textureLoad(input_color, clamp(coords, vec2<i32>(0), max_coords), 0).rgb;
+}
+
+// Luma times 2
+fn luma(color: vec3<f32>) -> f32 {
+    return color.b * 0.5 + (color.r * 0.5 + color.g);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn rcas(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (any(id.xy >= vec2<u32>(textureDimensions(output_color)))) {
+        return;
+    }
+    let coords = vec2<i32>(id.xy);
+
+    // Cross shaped neighborhood
+    //    b
+    //  d e f
+    //    h
+    let center = textureLoad(input_color, coords, 0);
+    let b = load(coords + vec2<i32>(0, -1));
+    let d = load(coords + vec2<i32>(-1, 0));
+    let e = center.rgb;
+    let f = load(coords + vec2<i32>(1, 0));
+    let h = load(coords + vec2<i32>(0, 1));
+
+    // Soften the lobe where the neighborhood looks like noise
+    let b_luma = luma(b);
+    let d_luma = luma(d);
+    let e_luma = luma(e);
+    let f_luma = luma(f);
+    let h_luma = luma(h);
+    let luma_range = max(max(max(b_luma, d_luma), max(e_luma, f_luma)), h_luma)
+        - min(min(min(b_luma, d_luma), min(e_luma, f_luma)), h_luma);
+    var noise = abs(0.25 * (b_luma + d_luma + f_luma + h_luma) - e_luma) / max(luma_range, 1e-5);
+    noise = -0.5 * clamp(noise, 0.0, 1.0) + 1.0;
+
+    // Largest negative lobe that doesn't push any channel outside of the neighborhood's range
+    let min4 = min(min(b, d), min(f, h));
+    let max4 = max(max(b, d), max(f, h));
+    let hit_min = min(min4, e) / max(4.0 * max4, vec3<f32>(1e-5));
+    let hit_max = (1.0 - max(max4, e)) / min(4.0 * min4 - 4.0, vec3<f32>(-1e-5));
+    let lobe_rgb = max(-hit_min, hit_max);
+    // 0.1875 (0.25 - 1/16) is FSR_RCAS_LIMIT, which keeps the filter from inverting
+    var lobe = max(-0.1875, min(max(lobe_rgb.r, max(lobe_rgb.g, lobe_rgb.b)), 0.0));
+    lobe *= parameters.sharpness_scale * noise;
+
+    let color = (lobe * (b + d + f + h) + e) / (4.0 * lobe + 1.0);
+    textureStore(output_color, coords, vec4<f32>(color, center.a));
+}
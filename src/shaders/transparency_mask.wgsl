@@ -0,0 +1,55 @@
+// Builds FSR's transparency and composition mask from whichever sources are enabled, taking the
+// largest value any of them gives each pixel
+
+struct MaskParameters {
+    mask_size: vec2<f32>,
+    // Bit 0: particles, bit 1: composited layer, bit 2: material IDs
+    sources: u32,
+    material_count: u32,
+    // Size of the region of each source covering the view
+    particles_region: vec2<f32>,
+    composited_layer_region: vec2<f32>,
+    material_ids_region: vec2<f32>,
+}
+
+@group(0) @binding(0) var particles: texture_2d<f32>;
+@group(0) @binding(1) var composited_layer: texture_2d<f32>;
+@group(0) @binding(2) var material_ids: texture_2d<u32>;
+@group(0) @binding(3) var<storage, read> material_values: array<f32>;
+@group(0) @binding(4) var<uniform> parameters: MaskParameters;
+
+@vertex
+fn vertex(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+}
+
+// Sources may be at a different resolution than the mask, e.g. UI drawn at display resolution
+fn source_coords(uv: vec2<f32>, region: vec2<f32>) -> vec2<i32> {
+    let coords = vec2<i32>(uv * region);
+    return clamp(coords, vec2<i32>(0), vec2<i32>(region) - 1);
+}
+
+@fragment
+fn fragment(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = position.xy / parameters.mask_size;
+    var mask = 0.0;
+
+    if ((parameters.sources & 1u) != 0u) {
+        let coords = source_coords(uv, parameters.particles_region);
+        mask = max(mask, textureLoad(particles, coords, 0).a);
+    }
+    if ((parameters.sources & 2u) != 0u) {
+        let coords = source_coords(uv, parameters.composited_layer_region);
+        mask = max(mask, textureLoad(composited_layer, coords, 0).a);
+    }
+    if ((parameters.sources & 4u) != 0u) {
+        let coords = source_coords(uv, parameters.material_ids_region);
+        let material_id = textureLoad(material_ids, coords, 0).r;
+        if (material_id < parameters.material_count) {
+            mask = max(mask, material_values[material_id]);
+        }
+    }
+
+    return vec4<f32>(clamp(mask, 0.0, 1.0), 0.0, 0.0, 0.0);
+}
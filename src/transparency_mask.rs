@@ -0,0 +1,419 @@
+use crate::viewport::region_fits;
+use crate::{Fsr2Texture, Fsr2WgpuError};
+use glam::UVec2;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBindingType, BufferUsages,
+    Color, ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d, FragmentState, LoadOp,
+    MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+const MASK_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+/// Where [`TransparencyMaskGenerator`] takes the mask from. Each pixel of the mask is the largest
+/// value given by any of the sources, or zero if none are.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TransparencyMaskSources<'a> {
+    /// Alpha blended particles drawn to a layer of their own. The mask takes their alpha.
+    pub particles: Option<TransparencyMaskSource<'a>>,
+    /// UI or any other layer composited over the scene. The mask takes its alpha.
+    pub composited_layer: Option<TransparencyMaskSource<'a>>,
+    /// A texture of a uint format holding a material ID per pixel. The mask takes the value set for
+    /// the material with [`TransparencyMaskGenerator::set_material_values`].
+    pub material_ids: Option<TransparencyMaskSource<'a>>,
+}
+
+/// One of [`TransparencyMaskSources`], covering the view with the region of `region` size at the
+/// top left corner of `texture`.
+///
+/// The region is read at the same relative position as the mask pixel, so it doesn't need to be
+/// at the input resolution, e.g. UI drawn at the upscaled resolution.
+#[derive(Clone, Copy, Debug)]
+pub struct TransparencyMaskSource<'a> {
+    pub texture: Fsr2Texture<'a>,
+    pub region: UVec2,
+}
+
+/// Builds the transparency and composition mask of [`crate::Fsr2RenderParameters`] from common
+/// sources.
+///
+/// The mask is an R8Unorm texture at the max input resolution, owned by the generator, of which
+/// the input resolution region at the top left corner is rebuilt each time. Call
+/// [`TransparencyMaskGenerator::resize`] along with recreating the context for a new max input
+/// resolution.
+pub struct TransparencyMaskGenerator {
+    bind_group_layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+    /// Bound in place of sources that aren't given.
+    placeholder_float: TextureView,
+    placeholder_uint: TextureView,
+    material_values: Buffer,
+    material_count: u32,
+    mask: Texture,
+    mask_view: TextureView,
+}
+
+impl TransparencyMaskGenerator {
+    pub fn new(device: &Device, max_input_resolution: UVec2) -> Self {
+        let texture_entry = |binding, sample_type| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type,
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let float = TextureSampleType::Float { filterable: false };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fsr2_transparency_mask_bind_group_layout"),
+            entries: &[
+                texture_entry(0, float),
+                texture_entry(1, float),
+                texture_entry(2, TextureSampleType::Uint),
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("fsr2_transparency_mask_shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/transparency_mask.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fsr2_transparency_mask_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("fsr2_transparency_mask_pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &module,
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: "fragment",
+                targets: &[Some(ColorTargetState {
+                    format: MASK_FORMAT,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        let mask = mask_texture(device, max_input_resolution);
+        let mask_view = mask.create_view(&TextureViewDescriptor::default());
+
+        Self {
+            bind_group_layout,
+            pipeline,
+            placeholder_float: placeholder(device, TextureFormat::Rgba8Unorm),
+            placeholder_uint: placeholder(device, TextureFormat::R32Uint),
+            material_values: material_values_buffer(device, &[]),
+            material_count: 0,
+            mask,
+            mask_view,
+        }
+    }
+
+    /// Reallocate the mask for a new max input resolution.
+    pub fn resize(&mut self, device: &Device, max_input_resolution: UVec2) {
+        self.mask = mask_texture(device, max_input_resolution);
+        self.mask_view = self.mask.create_view(&TextureViewDescriptor::default());
+    }
+
+    /// Mask values indexed by material ID, for [`TransparencyMaskSources::material_ids`]. IDs
+    /// without a value give zero.
+    pub fn set_material_values(&mut self, device: &Device, values: &[f32]) {
+        self.material_values = material_values_buffer(device, values);
+        self.material_count = values.len() as u32;
+    }
+
+    /// Build the mask at `input_resolution` from `sources`.
+    pub fn record(
+        &mut self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        sources: TransparencyMaskSources,
+        input_resolution: UVec2,
+    ) -> Result<Fsr2Texture<'_>, Fsr2WgpuError> {
+        if !region_fits(&self.mask, UVec2::ZERO, input_resolution) {
+            return Err(Fsr2WgpuError::RegionOutOfBounds);
+        }
+        let all_sources = [
+            sources.particles,
+            sources.composited_layer,
+            sources.material_ids,
+        ];
+        for source in all_sources.into_iter().flatten() {
+            if !region_fits(source.texture.texture, UVec2::ZERO, source.region) {
+                return Err(Fsr2WgpuError::RegionOutOfBounds);
+            }
+        }
+        for source in [sources.particles, sources.composited_layer]
+            .into_iter()
+            .flatten()
+        {
+            let format = source.texture.texture.format();
+            if !matches!(
+                format.describe().sample_type,
+                TextureSampleType::Float { .. }
+            ) {
+                return Err(Fsr2WgpuError::UnsupportedFormat(format));
+            }
+        }
+        if let Some(material_ids) = sources.material_ids {
+            let format = material_ids.texture.texture.format();
+            if format.describe().sample_type != TextureSampleType::Uint {
+                return Err(Fsr2WgpuError::UnsupportedFormat(format));
+            }
+        }
+
+        let parameters = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fsr2_transparency_mask_parameters"),
+            contents: &mask_parameters(
+                input_resolution,
+                all_sources.map(|source| source.map(|source| source.region)),
+                self.material_count,
+            ),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fsr2_transparency_mask_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: source_view(sources.particles, &self.placeholder_float),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: source_view(sources.composited_layer, &self.placeholder_float),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: source_view(sources.material_ids, &self.placeholder_uint),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: self.material_values.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: parameters.as_entire_binding(),
+                },
+            ],
+        });
+
+        {
+            let mut pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("fsr2_transparency_mask"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &self.mask_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_viewport(
+                0.0,
+                0.0,
+                input_resolution.x as f32,
+                input_resolution.y as f32,
+                0.0,
+                1.0,
+            );
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        Ok(Fsr2Texture {
+            texture: &self.mask,
+            view: &self.mask_view,
+        })
+    }
+}
+
+/// The contents of the shader's `MaskParameters`, given the region of each source that's enabled.
+fn mask_parameters(mask_size: UVec2, regions: [Option<UVec2>; 3], material_count: u32) -> Vec<u8> {
+    let source_bits = regions.iter().enumerate().fold(0u32, |bits, (i, region)| {
+        bits | (region.is_some() as u32) << i
+    });
+    let mut parameters = Vec::with_capacity(40);
+    parameters.extend_from_slice(&(mask_size.x as f32).to_ne_bytes());
+    parameters.extend_from_slice(&(mask_size.y as f32).to_ne_bytes());
+    parameters.extend_from_slice(&source_bits.to_ne_bytes());
+    parameters.extend_from_slice(&material_count.to_ne_bytes());
+    for region in regions {
+        let region = region.unwrap_or(UVec2::ZERO);
+        parameters.extend_from_slice(&(region.x as f32).to_ne_bytes());
+        parameters.extend_from_slice(&(region.y as f32).to_ne_bytes());
+    }
+    parameters
+}
+
+fn mask_texture(device: &Device, size: UVec2) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some("fsr2_transparency_and_composition_mask"),
+        size: Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: MASK_FORMAT,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+fn source_view<'a>(
+    source: Option<TransparencyMaskSource<'a>>,
+    placeholder: &'a TextureView,
+) -> BindingResource<'a> {
+    BindingResource::TextureView(source.map_or(placeholder, |source| source.texture.view))
+}
+
+fn placeholder(device: &Device, format: TextureFormat) -> TextureView {
+    device
+        .create_texture(&TextureDescriptor {
+            label: Some("fsr2_transparency_mask_placeholder"),
+            size: Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+        .create_view(&TextureViewDescriptor::default())
+}
+
+fn material_values_buffer(device: &Device, values: &[f32]) -> Buffer {
+    // Storage buffers can't be empty
+    let values = if values.is_empty() { &[0.0] } else { values };
+    let contents = values
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect::<Vec<_>>();
+    device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("fsr2_transparency_mask_material_values"),
+        contents: &contents,
+        usage: BufferUsages::STORAGE,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn mask_parameters_match_the_shader_layout() {
+        let parameters = mask_parameters(
+            UVec2::new(1280, 720),
+            [
+                None,
+                Some(UVec2::new(2560, 1440)),
+                Some(UVec2::new(640, 360)),
+            ],
+            3,
+        );
+
+        // MaskParameters: mask_size at 0, sources at 8, material_count at 12, then the regions of
+        // the particles, composited layer and material IDs, at 16, 24 and 32
+        assert_eq!(parameters.len(), 40);
+        assert_eq!(
+            (f32_at(&parameters, 0), f32_at(&parameters, 4)),
+            (1280.0, 720.0)
+        );
+        assert_eq!(u32_at(&parameters, 8), 0b110);
+        assert_eq!(u32_at(&parameters, 12), 3);
+        assert_eq!(
+            (f32_at(&parameters, 16), f32_at(&parameters, 20)),
+            (0.0, 0.0)
+        );
+        assert_eq!(
+            (f32_at(&parameters, 24), f32_at(&parameters, 28)),
+            (2560.0, 1440.0)
+        );
+        assert_eq!(
+            (f32_at(&parameters, 32), f32_at(&parameters, 36)),
+            (640.0, 360.0)
+        );
+
+        let shader = include_str!("shaders/transparency_mask.wgsl");
+        let fields = [
+            "mask_size: vec2<f32>",
+            "sources: u32",
+            "material_count: u32",
+            "particles_region: vec2<f32>",
+            "composited_layer_region: vec2<f32>",
+            "material_ids_region: vec2<f32>",
+        ]
+        .map(|field| shader.find(field).unwrap());
+        assert!(fields.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn source_bits_follow_the_shader() {
+        let bits = |regions| u32_at(&mask_parameters(UVec2::ONE, regions, 0), 8);
+        let region = Some(UVec2::ONE);
+
+        // Bit 0: particles, bit 1: composited layer, bit 2: material IDs
+        assert_eq!(bits([None, None, None]), 0);
+        assert_eq!(bits([region, None, None]), 0b001);
+        assert_eq!(bits([None, region, None]), 0b010);
+        assert_eq!(bits([None, None, region]), 0b100);
+        assert_eq!(bits([region, region, region]), 0b111);
+    }
+}
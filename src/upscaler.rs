@@ -0,0 +1,200 @@
+use crate::blit::Blitter;
+use crate::{
+    Fsr2Context, Fsr2Exposure, Fsr2QualityMode, Fsr2ReactiveMask, Fsr2RenderParameters,
+    Fsr2Texture, Fsr2WgpuError,
+};
+use glam::{Mat4, UVec2, Vec2};
+use std::ops::Deref;
+use std::time::Duration;
+use wgpu::{CommandEncoder, Device, FilterMode};
+
+/// A way of turning a frame rendered at some input resolution into an image at the upscaled
+/// resolution, so that renderers can switch between upscalers at runtime.
+pub trait Upscaler {
+    fn suggested_input_resolution(&self, quality_mode: Fsr2QualityMode) -> UVec2;
+
+    /// The sub-pixel offset to apply to the camera projection for this frame.
+    fn jitter(&self, input_resolution: UVec2, frame_index: i32) -> Vec2;
+
+    /// The bias to apply when sampling textures with mipmaps while rendering the input.
+    fn mip_bias(&self, input_resolution: UVec2) -> f32;
+
+    fn record(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        inputs: UpscalerInputs,
+    ) -> Result<(), Fsr2WgpuError>;
+}
+
+/// The part of [`Fsr2RenderParameters`] shared by every [`Upscaler`]. Upscalers ignore inputs
+/// they have no use for, like the exposure and masks only [`Fsr2Context`] reads.
+#[derive(Clone, Copy, Debug)]
+pub struct UpscalerInputs<'a> {
+    pub color: Fsr2Texture<'a>,
+    pub depth: Fsr2Texture<'a>,
+    pub motion_vectors: Fsr2Texture<'a>,
+    pub motion_vector_scale: Option<Vec2>,
+    pub exposure: Fsr2Exposure<'a>,
+    pub reactive_mask: Fsr2ReactiveMask<'a>,
+    pub transparency_and_composition_mask: Option<Fsr2Texture<'a>>,
+    /// Written with [`wgpu::TextureUsages::RENDER_ATTACHMENT`] by [`NativeUpscaler`] and
+    /// [`BilinearUpscaler`], and with [`wgpu::TextureUsages::STORAGE_BINDING`] by FSR.
+    pub output: Fsr2Texture<'a>,
+    pub input_resolution: UVec2,
+    pub sharpness: f32,
+    pub frame_delta_time: Option<Duration>,
+    pub reset: bool,
+    pub camera_near: f32,
+    pub camera_far: Option<f32>,
+    pub camera_fov_angle_vertical: f32,
+    pub camera_view: Option<Mat4>,
+    pub jitter_offset: Vec2,
+}
+
+impl<'a> From<UpscalerInputs<'a>> for Fsr2RenderParameters<'a> {
+    fn from(inputs: UpscalerInputs<'a>) -> Self {
+        Self {
+            color: inputs.color,
+            depth: inputs.depth,
+            motion_vectors: inputs.motion_vectors,
+            motion_vector_scale: inputs.motion_vector_scale,
+            exposure: inputs.exposure,
+            reactive_mask: inputs.reactive_mask,
+            transparency_and_composition_mask: inputs.transparency_and_composition_mask,
+            output: Some(inputs.output),
+            input_resolution: inputs.input_resolution,
+            input_offset: UVec2::ZERO,
+            output_offset: UVec2::ZERO,
+            sharpness: inputs.sharpness,
+            frame_delta_time: inputs.frame_delta_time,
+            reset: inputs.reset,
+            camera_near: inputs.camera_near,
+            camera_far: inputs.camera_far,
+            camera_fov_angle_vertical: inputs.camera_fov_angle_vertical,
+            camera_view: inputs.camera_view,
+            jitter_offset: inputs.jitter_offset,
+        }
+    }
+}
+
+impl<D: Deref<Target = Device>> Upscaler for Fsr2Context<D> {
+    fn suggested_input_resolution(&self, quality_mode: Fsr2QualityMode) -> UVec2 {
+        Fsr2Context::suggested_input_resolution(self, quality_mode)
+    }
+
+    fn jitter(&self, input_resolution: UVec2, frame_index: i32) -> Vec2 {
+        self.suggested_camera_jitter_offset(input_resolution, frame_index)
+    }
+
+    fn mip_bias(&self, input_resolution: UVec2) -> f32 {
+        self.suggested_mip_bias(input_resolution)
+    }
+
+    fn record(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        inputs: UpscalerInputs,
+    ) -> Result<(), Fsr2WgpuError> {
+        self.render(command_encoder, inputs.into())?;
+        Ok(())
+    }
+}
+
+/// Renders at the output resolution, and copies color to the output.
+pub struct NativeUpscaler<D: Deref<Target = Device>> {
+    device: D,
+    upscaled_resolution: UVec2,
+    blitter: Blitter,
+}
+
+impl<D: Deref<Target = Device>> NativeUpscaler<D> {
+    pub fn new(device: D, upscaled_resolution: UVec2) -> Self {
+        Self {
+            blitter: Blitter::new(&device, FilterMode::Nearest),
+            device,
+            upscaled_resolution,
+        }
+    }
+}
+
+impl<D: Deref<Target = Device>> Upscaler for NativeUpscaler<D> {
+    fn suggested_input_resolution(&self, _quality_mode: Fsr2QualityMode) -> UVec2 {
+        self.upscaled_resolution
+    }
+
+    fn jitter(&self, _input_resolution: UVec2, _frame_index: i32) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn mip_bias(&self, _input_resolution: UVec2) -> f32 {
+        0.0
+    }
+
+    fn record(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        inputs: UpscalerInputs,
+    ) -> Result<(), Fsr2WgpuError> {
+        blit_color(&mut self.blitter, &self.device, command_encoder, &inputs);
+        Ok(())
+    }
+}
+
+/// Renders at a lower resolution, and stretches color over the output with bilinear filtering.
+pub struct BilinearUpscaler<D: Deref<Target = Device>> {
+    device: D,
+    upscaled_resolution: UVec2,
+    blitter: Blitter,
+}
+
+impl<D: Deref<Target = Device>> BilinearUpscaler<D> {
+    pub fn new(device: D, upscaled_resolution: UVec2) -> Self {
+        Self {
+            blitter: Blitter::new(&device, FilterMode::Linear),
+            device,
+            upscaled_resolution,
+        }
+    }
+}
+
+impl<D: Deref<Target = Device>> Upscaler for BilinearUpscaler<D> {
+    fn suggested_input_resolution(&self, quality_mode: Fsr2QualityMode) -> UVec2 {
+        quality_mode.input_resolution(self.upscaled_resolution)
+    }
+
+    fn jitter(&self, _input_resolution: UVec2, _frame_index: i32) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn mip_bias(&self, input_resolution: UVec2) -> f32 {
+        // Without temporal accumulation there is no extra detail to recover, so sample the mips
+        // matching the input resolution
+        (input_resolution.x as f32 / self.upscaled_resolution.x as f32).log2()
+    }
+
+    fn record(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        inputs: UpscalerInputs,
+    ) -> Result<(), Fsr2WgpuError> {
+        blit_color(&mut self.blitter, &self.device, command_encoder, &inputs);
+        Ok(())
+    }
+}
+
+fn blit_color(
+    blitter: &mut Blitter,
+    device: &Device,
+    command_encoder: &mut CommandEncoder,
+    inputs: &UpscalerInputs,
+) {
+    let color_size = UVec2::new(inputs.color.texture.width(), inputs.color.texture.height());
+    blitter.blit(
+        device,
+        command_encoder,
+        inputs.color.view,
+        inputs.input_resolution.as_vec2() / color_size.as_vec2(),
+        inputs.output.view,
+        inputs.output.texture.format(),
+    );
+}
@@ -0,0 +1,329 @@
+use crate::Fsr2Texture;
+use glam::UVec2;
+use std::collections::HashMap;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferUsages,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    Extent3d, ImageCopyTexture, Origin3d, PipelineLayoutDescriptor, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, StorageTextureAccess, Texture, TextureAspect, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension,
+};
+
+const CROPPED_DEPTH_FORMAT: TextureFormat = TextureFormat::R32Float;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum CroppedInput {
+    Color,
+    Depth,
+    MotionVectors,
+    ReactiveMask,
+    TransparencyAndCompositionMask,
+}
+
+/// FSR reads its inputs from the top left corner of each texture, and writes to the whole of its
+/// output. This moves regions elsewhere in those textures to and from textures of its own.
+///
+/// Most inputs are copied as is. Textures of depth formats can only be copied whole, so those are
+/// instead cropped by a compute pass into a float texture.
+pub(crate) struct ViewportMapper {
+    max_input_resolution: UVec2,
+    upscaled_resolution: UVec2,
+    depth_bind_group_layout: BindGroupLayout,
+    depth_pipeline: ComputePipeline,
+    depth: Texture,
+    depth_view: TextureView,
+    inputs: HashMap<CroppedInput, (Texture, TextureView)>,
+    output: Option<(Texture, TextureView)>,
+}
+
+impl ViewportMapper {
+    pub fn new(device: &Device, max_input_resolution: UVec2, upscaled_resolution: UVec2) -> Self {
+        let depth_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("fsr2_crop_depth_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Depth,
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: CROPPED_DEPTH_FORMAT,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("fsr2_crop_depth_shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/crop_depth.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("fsr2_crop_depth_pipeline_layout"),
+            bind_group_layouts: &[&depth_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let depth_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("fsr2_crop_depth_pipeline"),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: "crop_depth",
+        });
+
+        let depth = create_texture(
+            device,
+            "fsr2_cropped_depth",
+            max_input_resolution,
+            CROPPED_DEPTH_FORMAT,
+            TextureUsages::STORAGE_BINDING,
+        );
+
+        Self {
+            max_input_resolution,
+            upscaled_resolution,
+            depth_bind_group_layout,
+            depth_pipeline,
+            depth_view: depth.create_view(&TextureViewDescriptor::default()),
+            depth,
+            inputs: HashMap::new(),
+            output: None,
+        }
+    }
+
+    /// Allocate the texture for `input` to be copied into, if there isn't one of `format` already.
+    pub fn prepare_input(&mut self, device: &Device, input: CroppedInput, format: TextureFormat) {
+        if let Some((texture, _)) = self.inputs.get(&input) {
+            if texture.format() == format {
+                return;
+            }
+        }
+
+        let texture = create_texture(
+            device,
+            "fsr2_cropped_input",
+            self.max_input_resolution,
+            format,
+            TextureUsages::COPY_DST,
+        );
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        self.inputs.insert(input, (texture, view));
+    }
+
+    /// Allocate the texture for FSR to write to, if there isn't one of `format` already.
+    /// `encoded` outputs are rendered to when encoding to the descriptor's color space.
+    pub fn prepare_output(&mut self, device: &Device, format: TextureFormat, encoded: bool) {
+        if let Some((texture, _)) = &self.output {
+            if texture.format() == format {
+                return;
+            }
+        }
+
+        let mut usage = TextureUsages::STORAGE_BINDING;
+        if encoded {
+            usage |= TextureUsages::RENDER_ATTACHMENT;
+        }
+        let texture = create_texture(
+            device,
+            "fsr2_uncropped_output",
+            self.upscaled_resolution,
+            format,
+            usage,
+        );
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        self.output = Some((texture, view));
+    }
+
+    /// Copy the region of `texture` at `offset` to the top left corner of the texture prepared for
+    /// `input` with [`ViewportMapper::prepare_input`].
+    pub fn crop_input(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        input: CroppedInput,
+        texture: &Fsr2Texture,
+        offset: UVec2,
+        size: UVec2,
+        layer: u32,
+    ) -> Fsr2Texture<'_> {
+        let (cropped, cropped_view) = &self.inputs[&input];
+        command_encoder.copy_texture_to_texture(
+            ImageCopyTexture {
+                texture: texture.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: offset.x,
+                    y: offset.y,
+                    z: array_layer(texture.texture, layer),
+                },
+                aspect: TextureAspect::All,
+            },
+            ImageCopyTexture {
+                texture: cropped,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            extent(size),
+        );
+
+        Fsr2Texture {
+            texture: cropped,
+            view: cropped_view,
+        }
+    }
+
+    pub fn crop_depth(
+        &self,
+        device: &Device,
+        command_encoder: &mut CommandEncoder,
+        depth: &Fsr2Texture,
+        offset: UVec2,
+        size: UVec2,
+    ) -> Fsr2Texture<'_> {
+        let mut parameters = Vec::with_capacity(16);
+        for value in [offset.x, offset.y, size.x, size.y] {
+            parameters.extend_from_slice(&value.to_ne_bytes());
+        }
+        let parameters = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("fsr2_crop_depth_parameters"),
+            contents: &parameters,
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("fsr2_crop_depth_bind_group"),
+            layout: &self.depth_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(depth.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&self.depth_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: parameters.as_entire_binding(),
+                },
+            ],
+        });
+
+        {
+            let mut pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("fsr2_crop_depth"),
+            });
+            pass.set_pipeline(&self.depth_pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let workgroups = (size + 7) / 8;
+            pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+
+        Fsr2Texture {
+            texture: &self.depth,
+            view: &self.depth_view,
+        }
+    }
+
+    /// The texture prepared with [`ViewportMapper::prepare_output`].
+    pub fn output(&self) -> Fsr2Texture<'_> {
+        let (texture, view) = self.output.as_ref().unwrap();
+        Fsr2Texture { texture, view }
+    }
+
+    /// Copy what FSR wrote to [`ViewportMapper::output`] to the region of `output` at `offset`.
+    pub fn place_output(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        output: &Fsr2Texture,
+        offset: UVec2,
+        layer: u32,
+    ) {
+        command_encoder.copy_texture_to_texture(
+            ImageCopyTexture {
+                texture: &self.output.as_ref().unwrap().0,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyTexture {
+                texture: output.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: offset.x,
+                    y: offset.y,
+                    z: array_layer(output.texture, layer),
+                },
+                aspect: TextureAspect::All,
+            },
+            extent(self.upscaled_resolution),
+        );
+    }
+}
+
+pub(crate) fn is_depth_format(format: TextureFormat) -> bool {
+    format.describe().sample_type == TextureSampleType::Depth
+}
+
+/// Whether a region of `size` at `offset` lies within `texture`.
+pub(crate) fn region_fits(texture: &Texture, offset: UVec2, size: UVec2) -> bool {
+    let end = offset + size;
+    end.x <= texture.width() && end.y <= texture.height()
+}
+
+pub(crate) fn array_layer(texture: &Texture, layer: u32) -> u32 {
+    if texture.depth_or_array_layers() > 1 {
+        layer
+    } else {
+        0
+    }
+}
+
+fn extent(size: UVec2) -> Extent3d {
+    Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+    }
+}
+
+fn create_texture(
+    device: &Device,
+    label: &str,
+    size: UVec2,
+    format: TextureFormat,
+    usage: TextureUsages,
+) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some(label),
+        size: extent(size),
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        // Copyable for captures
+        usage: usage | TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
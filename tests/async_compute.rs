@@ -0,0 +1,377 @@
+//! Async compute: FSR recorded into a command buffer of the compute queue family, on a wgpu device
+//! created over a Vulkan device with queues of both the graphics and compute families.
+//!
+//! Skipped when no Vulkan adapter is available. On adapters without a separate compute family,
+//! both queues are of the graphics family and no ownership transfers are recorded.
+
+use ash::vk;
+use fsr2_wgpu::{
+    Fsr2Context, Fsr2ContextDescriptor, Fsr2Exposure, Fsr2QueueFamilies, Fsr2ReactiveMask,
+    Fsr2RenderParameters, Fsr2Texture, Fsr2WgpuError,
+};
+use glam::{UVec2, Vec2};
+use std::num::NonZeroU32;
+use std::sync::mpsc;
+use std::time::Duration;
+use wgpu::{
+    Adapter, Backends, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device,
+    DeviceDescriptor, Extent3d, Features, ImageCopyBuffer, ImageDataLayout, Instance,
+    InstanceDescriptor, Limits, Maintain, MapMode, Queue, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use wgpu_core::api::Vulkan;
+
+const INPUT_RESOLUTION: UVec2 = UVec2::new(64, 64);
+const UPSCALED_RESOLUTION: UVec2 = UVec2::new(128, 128);
+const COLOR: u8 = 128;
+
+struct AsyncComputeDevice {
+    adapter: Adapter,
+    device: Device,
+    queue: Queue,
+    raw_device: ash::Device,
+    compute_queue: vk::Queue,
+    queue_families: Fsr2QueueFamilies,
+}
+
+#[test]
+fn render_on_compute_queue() {
+    let Some(async_compute_device) = async_compute_device() else {
+        eprintln!("Skipping render_on_compute_queue: no Vulkan adapter available");
+        return;
+    };
+    let AsyncComputeDevice {
+        adapter,
+        device,
+        queue,
+        raw_device,
+        compute_queue,
+        queue_families,
+    } = &async_compute_device;
+
+    let mut context = Fsr2Context::new(
+        device,
+        adapter,
+        Fsr2ContextDescriptor::new(INPUT_RESOLUTION, UPSCALED_RESOLUTION)
+            .queue_families(*queue_families),
+    )
+    .unwrap();
+
+    let texel_count = (INPUT_RESOLUTION.x * INPUT_RESOLUTION.y) as usize;
+    let color = upload(
+        device,
+        queue,
+        INPUT_RESOLUTION,
+        TextureFormat::Rgba8Unorm,
+        &[COLOR, COLOR, COLOR, 255].repeat(texel_count),
+    );
+    let depth = upload(
+        device,
+        queue,
+        INPUT_RESOLUTION,
+        TextureFormat::R32Float,
+        &0.5f32.to_ne_bytes().repeat(texel_count),
+    );
+    let motion_vectors = upload(
+        device,
+        queue,
+        INPUT_RESOLUTION,
+        TextureFormat::Rg32Float,
+        &[0; 8].repeat(texel_count),
+    );
+    let output = create_texture(
+        device,
+        UPSCALED_RESOLUTION,
+        TextureFormat::Rgba8Unorm,
+        TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+    );
+    let output_view = output.create_view(&TextureViewDescriptor::default());
+
+    let parameters = Fsr2RenderParameters {
+        color: fsr2_texture(&color),
+        depth: fsr2_texture(&depth),
+        motion_vectors: fsr2_texture(&motion_vectors),
+        motion_vector_scale: None,
+        exposure: Fsr2Exposure::AutoExposure,
+        reactive_mask: Fsr2ReactiveMask::NoMask,
+        transparency_and_composition_mask: None,
+        output: Some(Fsr2Texture {
+            texture: &output,
+            view: &output_view,
+        }),
+        input_resolution: INPUT_RESOLUTION,
+        input_offset: UVec2::ZERO,
+        output_offset: UVec2::ZERO,
+        sharpness: 0.0,
+        frame_delta_time: Some(Duration::from_secs_f32(1.0 / 60.0)),
+        reset: true,
+        camera_near: 0.1,
+        camera_far: Some(100.0),
+        camera_fov_angle_vertical: 1.0,
+        camera_view: None,
+        jitter_offset: Vec2::ZERO,
+    };
+
+    // A wgpu command encoder is always of the graphics family
+    if queue_families.is_async() {
+        let mut command_encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+        assert!(matches!(
+            context.render(&mut command_encoder, parameters),
+            Err(Fsr2WgpuError::AsyncComputeEncoder)
+        ));
+    }
+
+    // Graphics: release the inputs and output to the compute queue
+    let mut command_encoder =
+        device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    context.release_to_compute(&mut command_encoder, &parameters);
+    queue.submit([command_encoder.finish()]);
+    device.poll(Maintain::Wait);
+
+    // Compute: dispatch FSR, once the graphics submission has completed
+    unsafe {
+        let command_pool = raw_device
+            .create_command_pool(
+                &vk::CommandPoolCreateInfo::builder().queue_family_index(queue_families.compute),
+                None,
+            )
+            .unwrap();
+        let command_buffer = raw_device
+            .allocate_command_buffers(
+                &vk::CommandBufferAllocateInfo::builder()
+                    .command_pool(command_pool)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(1),
+            )
+            .unwrap()[0];
+        raw_device
+            .begin_command_buffer(
+                command_buffer,
+                &vk::CommandBufferBeginInfo::builder()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )
+            .unwrap();
+        context
+            .render_on_compute(command_buffer, parameters)
+            .unwrap();
+        raw_device.end_command_buffer(command_buffer).unwrap();
+
+        let fence = raw_device
+            .create_fence(&vk::FenceCreateInfo::default(), None)
+            .unwrap();
+        raw_device
+            .queue_submit(
+                *compute_queue,
+                &[vk::SubmitInfo::builder()
+                    .command_buffers(&[command_buffer])
+                    .build()],
+                fence,
+            )
+            .unwrap();
+        raw_device
+            .wait_for_fences(&[fence], true, u64::MAX)
+            .unwrap();
+        raw_device.destroy_fence(fence, None);
+        raw_device.destroy_command_pool(command_pool, None);
+    }
+
+    // Graphics: acquire the output back, once the compute submission has completed, and read it
+    let mut command_encoder =
+        device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    context.acquire_from_compute(&mut command_encoder, &parameters);
+    queue.submit([command_encoder.finish()]);
+
+    let image = read_back(device, queue, &output);
+    let mean = image
+        .chunks(4)
+        .map(|texel| texel[0] as f64 / 255.0)
+        .sum::<f64>()
+        / (image.len() / 4) as f64;
+    assert!(
+        (mean - COLOR as f64 / 255.0).abs() < 0.1,
+        "Mean output of {mean:.3} doesn't match the flat input color"
+    );
+}
+
+/// A wgpu device over a Vulkan device with a queue of the graphics family, used by wgpu, and one
+/// of the compute family, as described on [`Fsr2ContextDescriptor::queue_families`].
+fn async_compute_device() -> Option<AsyncComputeDevice> {
+    let instance = Instance::new(InstanceDescriptor {
+        backends: Backends::VULKAN,
+        ..Default::default()
+    });
+    let adapter = instance.enumerate_adapters(Backends::VULKAN).next()?;
+    let queue_families = Fsr2QueueFamilies::from_adapter(&adapter)?;
+    let features = Features::empty();
+
+    unsafe {
+        let (open_device, raw_device) = adapter.as_hal::<Vulkan, _, _>(|hal_adapter| {
+            let hal_adapter = hal_adapter?;
+            let instance = hal_adapter.shared_instance().raw_instance();
+
+            let extensions = hal_adapter.required_device_extensions(features);
+            let extension_names = extensions
+                .iter()
+                .map(|extension| extension.as_ptr())
+                .collect::<Vec<_>>();
+            let mut physical_device_features =
+                hal_adapter.physical_device_features(&extensions, features);
+
+            let priorities = [1.0];
+            let mut families = vec![queue_families.graphics];
+            if queue_families.is_async() {
+                families.push(queue_families.compute);
+            }
+            let queue_infos = families
+                .iter()
+                .map(|&family| {
+                    vk::DeviceQueueCreateInfo::builder()
+                        .queue_family_index(family)
+                        .queue_priorities(&priorities)
+                        .build()
+                })
+                .collect::<Vec<_>>();
+            let device_info = vk::DeviceCreateInfo::builder()
+                .queue_create_infos(&queue_infos)
+                .enabled_extension_names(&extension_names);
+            let device_info = physical_device_features.add_to_device_create_builder(device_info);
+
+            let raw_device = instance
+                .create_device(hal_adapter.raw_physical_device(), &device_info, None)
+                .ok()?;
+            let open_device = hal_adapter
+                .device_from_raw(
+                    raw_device.clone(),
+                    true,
+                    &extensions,
+                    features,
+                    queue_families.graphics,
+                    0,
+                )
+                .ok()?;
+            Some((open_device, raw_device))
+        })?;
+
+        let (device, queue) = adapter
+            .create_device_from_hal(
+                open_device,
+                &DeviceDescriptor {
+                    label: None,
+                    features,
+                    limits: Limits::default(),
+                },
+                None,
+            )
+            .ok()?;
+        // Without a separate compute family, both sides use wgpu's own queue, one at a time
+        let compute_queue = raw_device.get_device_queue(queue_families.compute, 0);
+
+        Some(AsyncComputeDevice {
+            adapter,
+            device,
+            queue,
+            raw_device,
+            compute_queue,
+            queue_families,
+        })
+    }
+}
+
+fn upload(
+    device: &Device,
+    queue: &Queue,
+    size: UVec2,
+    format: TextureFormat,
+    data: &[u8],
+) -> (Texture, TextureView) {
+    let texture = create_texture(
+        device,
+        size,
+        format,
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+    );
+    queue.write_texture(
+        texture.as_image_copy(),
+        data,
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(data.len() as u32 / size.y),
+            rows_per_image: None,
+        },
+        extent(size),
+    );
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    (texture, view)
+}
+
+fn read_back(device: &Device, queue: &Queue, texture: &Texture) -> Vec<u8> {
+    let bytes_per_row = texture.width() * 4;
+    let padded_bytes_per_row =
+        bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (padded_bytes_per_row * texture.height()) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut command_encoder =
+        device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    command_encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        extent(UVec2::new(texture.width(), texture.height())),
+    );
+    queue.submit([command_encoder.finish()]);
+
+    let (sender, receiver) = mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, move |result| sender.send(result).unwrap());
+    device.poll(Maintain::Wait);
+    receiver.recv().unwrap().unwrap();
+
+    let data = slice.get_mapped_range();
+    data.chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..bytes_per_row as usize])
+        .copied()
+        .collect()
+}
+
+fn create_texture(
+    device: &Device,
+    size: UVec2,
+    format: TextureFormat,
+    usage: TextureUsages,
+) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: None,
+        size: extent(size),
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    })
+}
+
+fn fsr2_texture((texture, view): &(Texture, TextureView)) -> Fsr2Texture<'_> {
+    Fsr2Texture { texture, view }
+}
+
+fn extent(size: UVec2) -> Extent3d {
+    Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+    }
+}
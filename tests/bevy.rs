@@ -0,0 +1,134 @@
+//! Runs [`Fsr2Plugin`] in a headless Bevy app, rendering to an image, and checks that the camera
+//! was rendered at the input resolution for upscaling. Skipped when no Vulkan adapter is available.
+
+use bevy::core_pipeline::prepass::{DepthPrepass, ViewPrepassTextures};
+use bevy::prelude::*;
+use bevy::render::camera::{ExtractedCamera, RenderTarget};
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::settings::{Backends, WgpuSettings};
+use bevy::render::{RenderApp, RenderPlugin, RenderSet};
+use bevy::window::ExitCondition;
+use fsr2_wgpu::{Fsr2CameraInfo, Fsr2Plugin, Fsr2QualityMode, Fsr2Settings};
+use std::sync::{Arc, Mutex};
+
+/// What the render world saw of the camera on the last frame: the size of its viewport, which the
+/// plugin only shrinks to the input resolution once it has an `Fsr2Context` for the camera, and
+/// whether it had the depth prepass the upscaling node reads.
+#[derive(Resource, Clone, Default)]
+struct RenderedCamera(Arc<Mutex<Option<(UVec2, bool)>>>);
+
+#[test]
+fn upscales_camera() {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::VULKAN,
+        ..Default::default()
+    });
+    if pollster::block_on(instance.request_adapter(&Default::default())).is_none() {
+        eprintln!("No Vulkan adapter available, skipping");
+        return;
+    }
+
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                wgpu_settings: WgpuSettings {
+                    backends: Some(Backends::VULKAN),
+                    ..default()
+                },
+            }),
+    )
+    .add_plugin(Fsr2Plugin)
+    .insert_resource(Msaa::Off)
+    .add_startup_system(setup);
+
+    let rendered_camera = RenderedCamera::default();
+    app.sub_app_mut(RenderApp)
+        .insert_resource(rendered_camera.clone())
+        .add_system(record_rendered_camera.in_set(RenderSet::Queue));
+
+    for _ in 0..8 {
+        app.update();
+    }
+
+    let mut cameras = app.world.query::<&Fsr2CameraInfo>();
+    let info = cameras.single(&app.world);
+    assert_eq!(info.input_resolution, UVec2::new(1280, 720));
+    assert!(info.mip_bias < -1.0);
+
+    let (viewport_size, has_depth) = rendered_camera
+        .0
+        .lock()
+        .unwrap()
+        .expect("the camera wasn't rendered");
+    assert_eq!(
+        viewport_size, info.input_resolution,
+        "no Fsr2Context for the camera"
+    );
+    assert!(has_depth, "no depth prepass for the upscaling node");
+}
+
+fn record_rendered_camera(
+    rendered_camera: Res<RenderedCamera>,
+    cameras: Query<(&ExtractedCamera, Option<&ViewPrepassTextures>)>,
+) {
+    for (camera, prepass_textures) in &cameras {
+        let viewport_size = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| viewport.physical_size)
+            .or(camera.physical_viewport_size);
+        let has_depth = prepass_textures.map_or(false, |textures| textures.depth.is_some());
+        if let Some(viewport_size) = viewport_size {
+            *rendered_camera.0.lock().unwrap() = Some((viewport_size, has_depth));
+        }
+    }
+}
+
+fn setup(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = Extent3d {
+        width: 1920,
+        height: 1080,
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("fsr2_test_target"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                hdr: true,
+                target: RenderTarget::Image(images.add(image)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 2.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..default()
+        },
+        DepthPrepass,
+        Fsr2Settings {
+            quality_mode: Fsr2QualityMode::Quality,
+            ..default()
+        },
+    ));
+}
@@ -0,0 +1,410 @@
+//! Golden image regression tests.
+//!
+//! Procedurally generated scenes are upscaled with FSR and the final frame is compared against a
+//! reference image in `tests/golden`. To keep results deterministic, the tests only run on a
+//! software Vulkan driver (e.g. lavapipe), and are skipped when none is available.
+//!
+//! Set `FSR2_BLESS=1` to (re)generate the reference images. Otherwise a missing reference fails
+//! the test.
+
+use fsr2_wgpu::{
+    Fsr2Context, Fsr2ContextDescriptor, Fsr2Exposure, Fsr2QualityMode, Fsr2ReactiveMask,
+    Fsr2RenderParameters, Fsr2Texture,
+};
+use glam::{UVec2, Vec2};
+use std::f32::consts::TAU;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+use wgpu::{
+    Adapter, Backends, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device,
+    DeviceDescriptor, DeviceType, Extent3d, Features, ImageCopyBuffer, ImageDataLayout, Instance,
+    InstanceDescriptor, Limits, Maintain, MapMode, Queue, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+const UPSCALED_RESOLUTION: UVec2 = UVec2::new(256, 256);
+const FRAME_COUNT: i32 = 16;
+const QUALITY_MODES: [Fsr2QualityMode; 3] = [
+    Fsr2QualityMode::Quality,
+    Fsr2QualityMode::Balanced,
+    Fsr2QualityMode::Performance,
+];
+/// Maximum root mean square error, over all channels normalized to [0, 1].
+const TOLERANCE: f64 = 0.02;
+
+#[test]
+fn moving_checkerboard() {
+    run_scene("moving_checkerboard", |position, frame| {
+        let velocity = Vec2::new(1.5, 0.75);
+        let offset = velocity * frame as f32;
+        let cell = ((position + offset) / 16.0).floor();
+        let value = if (cell.x + cell.y).rem_euclid(2.0) == 0.0 {
+            0.9
+        } else {
+            0.1
+        };
+
+        Sample {
+            color: [value; 3],
+            depth: 0.5,
+            motion_vector: velocity,
+            reactive: 0.0,
+        }
+    });
+}
+
+#[test]
+fn rotating_quad() {
+    run_scene("rotating_quad", |position, frame| {
+        let center = UPSCALED_RESOLUTION.as_vec2() / 2.0;
+        let angular_velocity = TAU / 180.0;
+        let rotation = Vec2::from_angle(-angular_velocity * frame as f32);
+        let local = rotation.rotate(position - center);
+
+        if local.abs().max_element() < 64.0 {
+            let previous = Vec2::from_angle(-angular_velocity).rotate(position - center) + center;
+            let color = if (local.x > 0.0) == (local.y > 0.0) {
+                [1.0, 0.3, 0.1]
+            } else {
+                [0.1, 0.3, 1.0]
+            };
+            Sample {
+                color,
+                depth: 0.25,
+                motion_vector: previous - position,
+                reactive: 0.0,
+            }
+        } else {
+            let gradient = position / UPSCALED_RESOLUTION.as_vec2();
+            Sample {
+                color: [gradient.x * 0.5, gradient.y * 0.5, 0.2],
+                depth: 1.0,
+                motion_vector: Vec2::ZERO,
+                reactive: 0.0,
+            }
+        }
+    });
+}
+
+#[test]
+fn particles_with_reactive_mask() {
+    run_scene("particles_with_reactive_mask", |position, frame| {
+        let background = Sample {
+            color: [0.05, 0.05, 0.1],
+            depth: 1.0,
+            motion_vector: Vec2::ZERO,
+            reactive: 0.0,
+        };
+
+        (0..24)
+            .map(|particle| {
+                let seed = particle as f32 * 12.9898;
+                let start = Vec2::new(seed.sin().fract().abs(), seed.cos().fract().abs())
+                    * UPSCALED_RESOLUTION.as_vec2();
+                let velocity = Vec2::new((seed * 3.1).sin(), (seed * 1.7).cos()) * 6.0;
+                let bounds = UPSCALED_RESOLUTION.as_vec2();
+                let center = ((start + velocity * frame as f32) % bounds + bounds) % bounds;
+                (center, velocity)
+            })
+            .find(|(center, _)| position.distance(*center) < 4.0)
+            .map_or(background, |(_, velocity)| Sample {
+                color: [1.0, 0.8, 0.3],
+                depth: 0.5,
+                motion_vector: -velocity,
+                reactive: 1.0,
+            })
+    });
+}
+
+/// Values of a scene at a point, with positions and motion vectors in upscaled resolution pixels.
+struct Sample {
+    color: [f32; 3],
+    depth: f32,
+    motion_vector: Vec2,
+    reactive: f32,
+}
+
+fn run_scene(name: &str, scene: impl Fn(Vec2, i32) -> Sample) {
+    let Some((adapter, device, queue)) = software_device() else {
+        eprintln!("Skipping {name}: no software Vulkan adapter available");
+        return;
+    };
+
+    for quality_mode in QUALITY_MODES {
+        let mut context = Fsr2Context::new(
+            &device,
+            &adapter,
+            Fsr2ContextDescriptor::new(UPSCALED_RESOLUTION, UPSCALED_RESOLUTION),
+        )
+        .unwrap();
+        let input_resolution = context.suggested_input_resolution(quality_mode);
+        let scale = UPSCALED_RESOLUTION.as_vec2() / input_resolution.as_vec2();
+
+        let output = create_texture(
+            &device,
+            UPSCALED_RESOLUTION,
+            TextureFormat::Rgba8Unorm,
+            TextureUsages::STORAGE_BINDING
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+        );
+        let output_view = output.create_view(&TextureViewDescriptor::default());
+
+        for frame in 0..FRAME_COUNT {
+            let jitter_offset = context.suggested_camera_jitter_offset(input_resolution, frame);
+
+            let mut color = Vec::new();
+            let mut depth = Vec::new();
+            let mut motion_vectors = Vec::new();
+            let mut reactive = Vec::new();
+            for y in 0..input_resolution.y {
+                for x in 0..input_resolution.x {
+                    let position = (Vec2::new(x as f32, y as f32) + 0.5 + jitter_offset) * scale;
+                    let sample = scene(position, frame);
+
+                    color.extend(sample.color.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8));
+                    color.push(255);
+                    depth.extend(sample.depth.to_ne_bytes());
+                    let motion_vector = sample.motion_vector / scale;
+                    motion_vectors.extend(motion_vector.x.to_ne_bytes());
+                    motion_vectors.extend(motion_vector.y.to_ne_bytes());
+                    reactive.push((sample.reactive * 255.0) as u8);
+                }
+            }
+
+            let color = upload(
+                &device,
+                &queue,
+                input_resolution,
+                TextureFormat::Rgba8Unorm,
+                &color,
+            );
+            let depth = upload(
+                &device,
+                &queue,
+                input_resolution,
+                TextureFormat::R32Float,
+                &depth,
+            );
+            let motion_vectors = upload(
+                &device,
+                &queue,
+                input_resolution,
+                TextureFormat::Rg32Float,
+                &motion_vectors,
+            );
+            let reactive = upload(
+                &device,
+                &queue,
+                input_resolution,
+                TextureFormat::R8Unorm,
+                &reactive,
+            );
+
+            let mut command_encoder =
+                device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+            context
+                .render(
+                    &mut command_encoder,
+                    Fsr2RenderParameters {
+                        color: fsr2_texture(&color),
+                        depth: fsr2_texture(&depth),
+                        motion_vectors: fsr2_texture(&motion_vectors),
+                        motion_vector_scale: None,
+                        exposure: Fsr2Exposure::AutoExposure,
+                        reactive_mask: Fsr2ReactiveMask::ManualMask(fsr2_texture(&reactive)),
+                        transparency_and_composition_mask: None,
+                        output: Some(Fsr2Texture {
+                            texture: &output,
+                            view: &output_view,
+                        }),
+                        input_resolution,
+                        input_offset: UVec2::ZERO,
+                        output_offset: UVec2::ZERO,
+                        sharpness: 0.0,
+                        frame_delta_time: Some(Duration::from_secs_f32(1.0 / 60.0)),
+                        reset: frame == 0,
+                        camera_near: 0.1,
+                        camera_far: Some(100.0),
+                        camera_fov_angle_vertical: 1.0,
+                        camera_view: None,
+                        jitter_offset,
+                    },
+                )
+                .unwrap();
+            queue.submit([command_encoder.finish()]);
+        }
+
+        let image = read_back(&device, &queue, &output);
+        compare_to_reference(&format!("{name}_{quality_mode:?}"), &image);
+    }
+}
+
+fn software_device() -> Option<(Adapter, Device, Queue)> {
+    let instance = Instance::new(InstanceDescriptor {
+        backends: Backends::VULKAN,
+        ..Default::default()
+    });
+    let adapter = instance
+        .enumerate_adapters(Backends::VULKAN)
+        .find(|adapter| adapter.get_info().device_type == DeviceType::Cpu)?;
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &DeviceDescriptor {
+            label: None,
+            features: Features::empty(),
+            limits: Limits::default(),
+        },
+        None,
+    ))
+    .ok()?;
+    Some((adapter, device, queue))
+}
+
+fn compare_to_reference(name: &str, image: &[u8]) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"));
+
+    if std::env::var_os("FSR2_BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(File::create(&path).unwrap()),
+            UPSCALED_RESOLUTION.x,
+            UPSCALED_RESOLUTION.y,
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(image)
+            .unwrap();
+        eprintln!("Wrote reference image {}", path.display());
+        return;
+    }
+
+    assert!(
+        path.exists(),
+        "{name}: missing reference image {}, run with FSR2_BLESS=1 to create it",
+        path.display()
+    );
+    let mut reader = png::Decoder::new(fs::File::open(&path).unwrap())
+        .read_info()
+        .unwrap();
+    let mut reference = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut reference).unwrap();
+    assert_eq!(reference.len(), image.len(), "{name}: size mismatch");
+
+    let squared_error = image
+        .iter()
+        .zip(&reference)
+        .map(|(&a, &b)| ((a as f64 - b as f64) / 255.0).powi(2))
+        .sum::<f64>();
+    let rmse = (squared_error / image.len() as f64).sqrt();
+    assert!(
+        rmse <= TOLERANCE,
+        "{name}: RMSE of {rmse:.4} against the reference exceeds the tolerance of {TOLERANCE}"
+    );
+}
+
+fn upload(
+    device: &Device,
+    queue: &Queue,
+    size: UVec2,
+    format: TextureFormat,
+    data: &[u8],
+) -> (Texture, TextureView) {
+    let texture = create_texture(
+        device,
+        size,
+        format,
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+    );
+    queue.write_texture(
+        texture.as_image_copy(),
+        data,
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(data.len() as u32 / size.y),
+            rows_per_image: None,
+        },
+        extent(size),
+    );
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    (texture, view)
+}
+
+fn read_back(device: &Device, queue: &Queue, texture: &Texture) -> Vec<u8> {
+    let bytes_per_row = texture.width() * 4;
+    let padded_bytes_per_row =
+        bytes_per_row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (padded_bytes_per_row * texture.height()) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut command_encoder =
+        device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    command_encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        extent(UVec2::new(texture.width(), texture.height())),
+    );
+    queue.submit([command_encoder.finish()]);
+
+    let (sender, receiver) = mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, move |result| sender.send(result).unwrap());
+    device.poll(Maintain::Wait);
+    receiver.recv().unwrap().unwrap();
+
+    let data = slice.get_mapped_range();
+    data.chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..bytes_per_row as usize])
+        .copied()
+        .collect()
+}
+
+fn create_texture(
+    device: &Device,
+    size: UVec2,
+    format: TextureFormat,
+    usage: TextureUsages,
+) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: None,
+        size: extent(size),
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    })
+}
+
+fn fsr2_texture((texture, view): &(Texture, TextureView)) -> Fsr2Texture<'_> {
+    Fsr2Texture { texture, view }
+}
+
+fn extent(size: UVec2) -> Extent3d {
+    Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+    }
+}